@@ -1,61 +1,298 @@
-use beamtable::BeamTable;
-use beamtable::Geomstr;
+mod output;
+
+use beamtable::{svg_paths, BeamTable, BoolOp, FillRule, Geomstr, SegmentKind, SvgPath};
 use clap::Parser;
+use output::GcodeOptions;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
 use std::path::PathBuf;
+use std::str::FromStr;
 use vsvg::{DocumentTrait, Draw, LayerTrait, PathTrait};
 
 #[derive(clap::Parser, Debug)]
-
 struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Union of the selected paths, each filled with its own fill-rule
+    Union(Unary),
+
+    /// Space inside both operands
+    Intersect(Binary),
+
+    /// Space inside operand a but not operand b
+    Difference(Binary),
+
+    /// Space inside exactly one of the operands
+    Xor(Binary),
+
+    /// Even-odd fill of the selected paths
+    Evenodd(Unary),
+
+    /// Nonzero winding fill of the selected paths
+    Nonzero(Unary),
+}
+
+#[derive(clap::Args, Debug)]
+struct Common {
     /// SVG file
     path: PathBuf,
 
-    /// Display the result
+    /// Display the result, in addition to the output of any format
     #[clap(long)]
     show: bool,
 
-    /// Save the result as SVG
+    /// Save the result, formats other than SVG are written to stdout without it
     #[clap(long)]
     save: Option<PathBuf>,
+
+    /// Output format
+    #[clap(long, value_enum, default_value_t = Format::Svg)]
+    format: Format,
+
+    /// Order the output lines to reduce travel
+    #[clap(long)]
+    greedy: bool,
+
+    /// G-code feed rate in mm/min
+    #[clap(long, default_value_t = 1000.0)]
+    feed: f64,
+
+    /// G-code power for an SVG layer, as LAYER:POWER where LAYER is the id of layer:ID selectors
+    #[clap(long, value_parser = parse_power)]
+    power: Vec<(f64, f64)>,
+
+    /// G-code power for layers without a --power
+    #[clap(long, default_value_t = 1000.0)]
+    default_power: f64,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum Format {
+    Dxf,
+    Gcode,
+    Hpgl,
+    Json,
+    Svg,
+}
+
+fn parse_power(s: &str) -> Result<(f64, f64), String> {
+    let (layer, power) = s
+        .split_once(':')
+        .ok_or_else(|| format!("expected LAYER:POWER, got {}", s))?;
+    let layer = layer.parse().map_err(|e| format!("{}: {}", s, e))?;
+    let power = power.parse().map_err(|e| format!("{}: {}", s, e))?;
+    Ok((layer, power))
+}
+
+#[derive(clap::Args, Debug)]
+struct Unary {
+    #[command(flatten)]
+    common: Common,
+
+    /// Paths to use, as layer:ID or path:INDEX, all paths if omitted
+    #[clap(short, long)]
+    select: Vec<Selector>,
+}
+
+#[derive(clap::Args, Debug)]
+struct Binary {
+    #[command(flatten)]
+    common: Common,
+
+    /// First operand, as layer:ID or path:INDEX
+    #[clap(short, required = true)]
+    a: Vec<Selector>,
+
+    /// Second operand, as layer:ID or path:INDEX
+    #[clap(short, required = true)]
+    b: Vec<Selector>,
+}
+
+/// Paths of the SVG by layer id or by path index, counted across all layers.
+#[derive(Clone, Debug)]
+enum Selector {
+    Layer(usize),
+    Path(usize),
+}
+
+impl FromStr for Selector {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, value) = s
+            .split_once(':')
+            .ok_or_else(|| format!("expected layer:ID or path:INDEX, got {}", s))?;
+        let value: usize = value.parse().map_err(|e| format!("{}: {}", s, e))?;
+        match kind {
+            "layer" => Ok(Selector::Layer(value)),
+            "path" => Ok(Selector::Path(value)),
+            _ => Err(format!("expected layer:ID or path:INDEX, got {}", s)),
+        }
+    }
+}
+
+impl Selector {
+    fn matches(&self, layer: usize, path: usize) -> bool {
+        match self {
+            Selector::Layer(id) => *id == layer,
+            Selector::Path(index) => *index == path,
+        }
+    }
+}
+
+/// Whether each path matches any of the selectors.
+fn select(layers: &[usize], selectors: &[Selector]) -> Vec<bool> {
+    (0..layers.len())
+        .map(|path| selectors.iter().any(|s| s.matches(layers[path], path)))
+        .collect()
+}
+
+/// Union of the fills of the selected paths, each with its own SVG fill-rule.
+fn operand(table: &BeamTable, paths: &[SvgPath], selected: &[bool]) -> BoolOp {
+    let empty = table.actives.iter().map(|a| vec![false; a.len() + 1]).collect();
+    let mut mask = BoolOp::new(empty);
+    for (index, path) in paths.iter().enumerate().filter(|(i, _)| selected[*i]) {
+        mask = mask
+            | match path.fill_rule {
+                FillRule::EvenOdd => table.evenodd_fill(index as f64),
+                FillRule::NonZero => table.nonzero_fill(index as f64),
+            };
+    }
+    mask
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     // use clap
     let args = Args::parse();
+    let common = match &args.command {
+        Command::Union(unary) | Command::Evenodd(unary) | Command::Nonzero(unary) => {
+            &unary.common
+        }
+        Command::Intersect(binary) | Command::Difference(binary) | Command::Xor(binary) => {
+            &binary.common
+        }
+    };
 
-    // load and flatten the document as we only support polyline
-    let doc = vsvg::Document::from_svg(&args.path, true)?.flatten(vsvg::DEFAULT_TOLERANCE);
+    // load the shapes, curves are flattened when the table is built
+    let paths = svg_paths(&common.path, vsvg::DEFAULT_TOLERANCE)?;
+    let layers: Vec<usize> = paths.iter().map(|path| path.layer).collect();
 
-    // convert everything to lines
+    // paths taking part in the operation, all of them if a unary command selects nothing
+    let used = match &args.command {
+        Command::Union(unary) | Command::Evenodd(unary) | Command::Nonzero(unary) => {
+            if unary.select.is_empty() {
+                vec![true; layers.len()]
+            } else {
+                select(&layers, &unary.select)
+            }
+        }
+        Command::Intersect(binary) | Command::Difference(binary) | Command::Xor(binary) => {
+            let a = select(&layers, &binary.a);
+            let b = select(&layers, &binary.b);
+            a.iter().zip(&b).map(|(a, b)| *a || *b).collect()
+        }
+    };
+
+    // gather the used paths, the settings of each segment is its path index
     let mut segments = Geomstr::new();
-    let mut idx = 0;
-    doc.layers.values().for_each(|layer| {
-        layer.paths.iter().for_each(|path| {
-            path.data.points().windows(2).for_each(|p| {
-                segments.line((p[0].x(), p[0].y()), (p[1].x(), p[1].y()), idx as f64);
-            });
-            idx += 1;
-        });
-    });
+    for (idx, path) in paths.iter().enumerate().filter(|(idx, _)| used[*idx]) {
+        segments.segments.extend(path.geometry.segments.iter().map(|segment| {
+            let mut segment = *segment;
+            segment.set_settings(idx as f64);
+            segment
+        }));
+    }
 
     // run scan beam algorithm
-    let mut beamtable = BeamTable::new(segments);
-    beamtable.build();
-    // let mask = beamtable.evenodd_fill(20.0);
-    let mask = beamtable.union_all();
-    let geom = beamtable.create(mask);
+    let mut beamtable = BeamTable::new(segments, None);
+    beamtable.build()?;
+    let mask = match &args.command {
+        Command::Union(_) => operand(&beamtable, &paths, &used),
+        Command::Evenodd(_) => beamtable.even_odd_ignoring_origin(),
+        Command::Nonzero(_) => beamtable.nonzero_all(),
+        Command::Intersect(binary) => {
+            operand(&beamtable, &paths, &select(&layers, &binary.a))
+                & operand(&beamtable, &paths, &select(&layers, &binary.b))
+        }
+        Command::Difference(binary) => {
+            operand(&beamtable, &paths, &select(&layers, &binary.a))
+                - operand(&beamtable, &paths, &select(&layers, &binary.b))
+        }
+        Command::Xor(binary) => {
+            operand(&beamtable, &paths, &select(&layers, &binary.a))
+                ^ operand(&beamtable, &paths, &select(&layers, &binary.b))
+        }
+    };
+    let mut geom = beamtable.create(mask, common.greedy)?;
+
+    // the output lines carry the SVG layer of their path, for the layers, powers and pens of
+    // the machine formats
+    for segment in &mut geom.segments {
+        if segment.kind() == SegmentKind::Line {
+            segment.set_settings(paths[segment.settings() as usize].layer as f64);
+        }
+    }
 
-    //
-    // visualize the result
-    //
+    match common.format {
+        Format::Svg => {
+            if let Some(path) = &common.save {
+                let mut doc = document(&geom);
 
+                // work around https://github.com/abey79/vsvg/issues/114
+                doc.metadata_mut().source = None;
+
+                doc.to_svg_file(path)?;
+            }
+        }
+        Format::Dxf => write_output(&common.save, |out| output::write_dxf(out, &geom))?,
+        Format::Gcode => {
+            let options = GcodeOptions {
+                feed: common.feed,
+                power: common.power.clone(),
+                default_power: common.default_power,
+            };
+            write_output(&common.save, |out| {
+                output::write_gcode(out, &geom, &options)
+            })?
+        }
+        Format::Hpgl => write_output(&common.save, |out| output::write_hpgl(out, &geom))?,
+        Format::Json => write_output(&common.save, |out| output::write_json(out, &geom))?,
+    }
+
+    if common.show {
+        vsvg_viewer::show(document(&geom).into())?;
+    }
+
+    Ok(())
+}
+
+/// Write a format other than SVG to the save file, or to stdout without one.
+fn write_output(
+    save: &Option<PathBuf>,
+    write: impl FnOnce(&mut Box<dyn Write>) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut out: Box<dyn Write> = match save {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    write(&mut out)?;
+    out.flush()
+}
+
+/// The result as a vsvg document for saving and display.
+fn document(geom: &Geomstr) -> vsvg::Document {
     // convert back to regular (not flattened) document, merge everything to layer 0 and normalize
     // line width and color
     let mut doc = vsvg::Document::default();
 
     let layer = doc.get_mut(1);
-    for line in geom.segments {
-        layer.line(line.0 .0, line.0 .1, line.4 .0, line.4 .1);
+    for line in &geom.segments {
+        let (start, end) = (line.start(), line.end());
+        layer.line(start.x, start.y, end.x, end.y);
     }
 
     doc.merge_layers();
@@ -71,16 +308,5 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     //     layer.circle(event.x, event.y, 0.5);
     // }
 
-    if let Some(path) = args.save {
-        // work around https://github.com/abey79/vsvg/issues/114
-        doc.metadata_mut().source = None;
-
-        doc.to_svg_file(path)?;
-    }
-
-    if args.show {
-        vsvg_viewer::show(doc.into())?;
-    }
-
-    Ok(())
+    doc
 }
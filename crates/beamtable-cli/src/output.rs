@@ -0,0 +1,141 @@
+use beamtable::{Geomstr, Matrix, Point, SegmentKind};
+use std::io::{self, Write};
+
+/// Millimeters per SVG pixel, `svg_paths` gives coordinates in 96 dpi pixels.
+const MM_PER_PX: f64 = 25.4 / 96.0;
+
+/// HPGL plotter units per millimeter.
+const HPGL_PER_MM: f64 = 40.0;
+
+/// Number of pens cycled through for HPGL layers, layers past it reuse the pens from the first.
+const PENS: usize = 8;
+
+/// Machine settings for G-code output.
+#[derive(Debug, Clone)]
+pub struct GcodeOptions {
+    /// Cutting feed rate in mm/min.
+    pub feed: f64,
+
+    /// Spindle/laser power for each layer by settings value.
+    pub power: Vec<(f64, f64)>,
+
+    /// Power for layers not in the power list.
+    pub default_power: f64,
+}
+
+impl GcodeOptions {
+    fn power(&self, settings: f64) -> f64 {
+        self.power
+            .iter()
+            .find(|(layer, _)| *layer == settings)
+            .map_or(self.default_power, |(_, power)| *power)
+    }
+}
+
+/// Internal: the lines of the geometry as start, end and settings.
+fn lines(geometry: &Geomstr) -> impl Iterator<Item = (Point, Point, f64)> + '_ {
+    geometry
+        .segments
+        .iter()
+        .filter(|s| s.kind() == SegmentKind::Line)
+        .map(|s| (s.start(), s.end(), s.settings()))
+}
+
+/// Internal: the geometry mirrored top to bottom within its bounding box. SVG y points down, DXF,
+/// G-code and HPGL y points up, so drawings keep their orientation and position.
+fn y_up(geometry: &Geomstr) -> Geomstr {
+    let mut flipped = geometry.clone();
+    if let Some((_, min_y, _, max_y)) = geometry.bbox() {
+        flipped.transform(&Matrix::scale(1.0, -1.0).then(&Matrix::translate(0.0, min_y + max_y)));
+    }
+    flipped
+}
+
+/// Lines as JSON, coordinates in document units with y pointing down as in the SVG.
+pub fn write_json(out: &mut impl Write, geometry: &Geomstr) -> io::Result<()> {
+    writeln!(out, "{{\"lines\": [")?;
+    let mut first = true;
+    for (start, end, settings) in lines(geometry) {
+        if !first {
+            writeln!(out, ",")?;
+        }
+        first = false;
+        write!(
+            out,
+            "  {{\"start\": [{}, {}], \"end\": [{}, {}], \"settings\": {}}}",
+            start.x, start.y, end.x, end.y, settings
+        )?;
+    }
+    writeln!(out, "\n]}}")
+}
+
+/// Lines as R12 DXF LINE entities in millimeters with y up, one DXF layer per settings value.
+pub fn write_dxf(out: &mut impl Write, geometry: &Geomstr) -> io::Result<()> {
+    writeln!(out, "0\nSECTION\n2\nENTITIES")?;
+    for (start, end, settings) in lines(&y_up(geometry)) {
+        writeln!(out, "0\nLINE\n8\n{}", settings)?;
+        writeln!(out, "10\n{:.4}\n20\n{:.4}\n30\n0.0", start.x * MM_PER_PX, start.y * MM_PER_PX)?;
+        writeln!(out, "11\n{:.4}\n21\n{:.4}\n31\n0.0", end.x * MM_PER_PX, end.y * MM_PER_PX)?;
+    }
+    writeln!(out, "0\nENDSEC\n0\nEOF")
+}
+
+/// Lines as G-code in millimeters with y up. Disconnected lines are joined with G0 travel moves, cutting
+/// moves carry the feed rate and the power of their layer. Uses dynamic laser power (M4).
+pub fn write_gcode(
+    out: &mut impl Write,
+    geometry: &Geomstr,
+    options: &GcodeOptions,
+) -> io::Result<()> {
+    writeln!(out, "G21\nG90\nM4 S0")?;
+    let mut position: Option<Point> = None;
+    for (start, end, settings) in lines(&y_up(geometry)) {
+        if position.as_ref() != Some(&start) {
+            writeln!(out, "G0 X{:.3} Y{:.3}", start.x * MM_PER_PX, start.y * MM_PER_PX)?;
+        }
+        writeln!(
+            out,
+            "G1 X{:.3} Y{:.3} F{} S{}",
+            end.x * MM_PER_PX,
+            end.y * MM_PER_PX,
+            options.feed,
+            options.power(settings)
+        )?;
+        position = Some(end);
+    }
+    writeln!(out, "M5\nG0 X0 Y0")
+}
+
+/// Lines as HPGL in plotter units with y up. Layers are drawn in turn, each with its own pen, taken in
+/// order of settings value and cycling through the pens.
+pub fn write_hpgl(out: &mut impl Write, geometry: &Geomstr) -> io::Result<()> {
+    let mut lines: Vec<(Point, Point, f64)> = lines(&y_up(geometry)).collect();
+    lines.sort_by(|a, b| a.2.total_cmp(&b.2));
+    let mut layers: Vec<f64> = lines.iter().map(|l| l.2).collect();
+    layers.dedup();
+
+    let units = |pt: &Point| {
+        (
+            (pt.x * MM_PER_PX * HPGL_PER_MM).round() as i64,
+            (pt.y * MM_PER_PX * HPGL_PER_MM).round() as i64,
+        )
+    };
+    writeln!(out, "IN;")?;
+    let mut pen: Option<usize> = None;
+    let mut position: Option<(i64, i64)> = None;
+    for (start, end, settings) in &lines {
+        let layer_pen = layers.iter().position(|l| l == settings).unwrap() % PENS + 1;
+        if pen != Some(layer_pen) {
+            writeln!(out, "SP{};", layer_pen)?;
+            pen = Some(layer_pen);
+            position = None;
+        }
+        let (start, end) = (units(start), units(end));
+        if position != Some(start) {
+            writeln!(out, "PU{},{};", start.0, start.1)?;
+        }
+        writeln!(out, "PD{},{};", end.0, end.1)?;
+        position = Some(end);
+    }
+    writeln!(out, "PU;SP0;")
+}
@@ -1,7 +1,7 @@
 //! Recreation of Georg Nees' ["Schotter" (1968-1970)](https://collections.vam.ac.uk/item/O221321/schotter-print-nees-georg/)
 //! using whiskers.
 
-use beamtable::{BeamTable, Geomstr};
+use beamtable::{svg_paths, BeamTable, Geomstr};
 use itertools::iproduct;
 use vsvg::{DocumentTrait, LayerTrait, PathTrait, DEFAULT_TOLERANCE};
 use whiskers::prelude::*;
@@ -61,27 +61,25 @@ impl App for WhiskersDemoSketch {
                     .rect(0., 0., self.box_size_cm, self.box_size_cm);
             });
         }
-        let doc = sketch.document_mut();
-        let doc = doc.flatten(DEFAULT_TOLERANCE);
-
-        // convert everything to lines
+        // read the sketch back through SVG, the settings of each segment is its path index so
+        // the union merges every box, curves are flattened when the table is built
+        let svg = sketch.document_mut().to_svg_string()?;
         let mut segments = Geomstr::new();
-        let mut idx = 0;
-        doc.layers.values().for_each(|layer| {
-            layer.paths.iter().for_each(|path| {
-                path.data.points().windows(2).for_each(|p| {
-                    segments.line((p[0].x(), p[0].y()), (p[1].x(), p[1].y()), idx as f64);
-                });
-                idx += 1;
-            });
-        });
+        for (idx, path) in svg_paths(svg, DEFAULT_TOLERANCE)?.into_iter().enumerate() {
+            segments
+                .segments
+                .extend(path.geometry.segments.into_iter().map(|mut segment| {
+                    segment.set_settings(idx as f64);
+                    segment
+                }));
+        }
 
         // run scan beam algorithm
-        let mut beamtable = BeamTable::new(segments);
-        beamtable.build();
+        let mut beamtable = BeamTable::new(segments, None);
+        beamtable.build()?;
         // let mask = beamtable.evenodd_fill(20.0);
         let mask = beamtable.union_all();
-        let geom = beamtable.create(mask, false); // greedy = false
+        let geom = beamtable.create(mask, false)?; // greedy = false
 
         //
         // visualize the result
@@ -94,7 +92,8 @@ impl App for WhiskersDemoSketch {
         let mut doc = vsvg::Document::default();
         let layer = doc.get_mut(1);
         for line in geom.segments {
-            layer.line(line.0 .0, line.0 .1, line.4 .0, line.4 .1);
+            let (start, end) = (line.start(), line.end());
+            layer.line(start.x, start.y, end.x, end.y);
         }
 
         doc.merge_layers();
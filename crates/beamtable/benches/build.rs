@@ -0,0 +1,39 @@
+//! Scaling of `BeamTable::build` on random short segments.
+//!
+//! Run with `cargo bench -p beamtable`. Segments are kept short and the area grows with the
+//! count so that the number of intersections stays roughly linear in the number of segments.
+
+use beamtable::{BeamTable, Geomstr};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::time::Instant;
+
+fn random_segments(count: usize, rng: &mut StdRng) -> Geomstr {
+    let size = (count as f64).sqrt() * 10.0;
+    let mut segments = Geomstr::new();
+    for i in 0..count {
+        let x = rng.gen_range(0.0..size);
+        let y = rng.gen_range(0.0..size);
+        let dx = rng.gen_range(-4.0..4.0);
+        let dy = rng.gen_range(-4.0..4.0);
+        segments.line((x, y), (x + dx, y + dy), (i % 10) as f64);
+    }
+    segments
+}
+
+fn main() {
+    let mut rng = StdRng::seed_from_u64(0);
+    for count in [1_000, 10_000, 100_000, 200_000] {
+        let segments = random_segments(count, &mut rng);
+        let mut table = BeamTable::new(segments, None);
+        let start = Instant::now();
+        table.build().unwrap();
+        let elapsed = start.elapsed();
+        println!(
+            "build {count:>7} segments: {:>10.3?} ({} events, {} intersections)",
+            elapsed,
+            table.events.len(),
+            table.intersections.len()
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use std::fmt;
+use std::ops::Index;
+
+/// Actives table, the y-ordered active segments for each beam. Stored CSR-style as offsets into
+/// one flat buffer so each beam costs its entries plus a single offset.
+#[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "ActivesParts")
+)]
+pub struct Actives {
+    offsets: Vec<usize>,
+    data: Vec<usize>,
+}
+
+impl Actives {
+    pub fn new() -> Actives {
+        Actives {
+            offsets: vec![0],
+            data: Vec::new(),
+        }
+    }
+
+    /// Number of beams.
+    pub fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Active segments for the beam.
+    pub fn get(&self, beam: usize) -> Option<&[usize]> {
+        if beam >= self.len() {
+            return None;
+        }
+        Some(&self.data[self.offsets[beam]..self.offsets[beam + 1]])
+    }
+
+    pub fn last(&self) -> Option<&[usize]> {
+        self.len().checked_sub(1).and_then(|beam| self.get(beam))
+    }
+
+    /// Append the active segments for the next beam.
+    pub fn push(&mut self, active: &[usize]) {
+        self.data.extend_from_slice(active);
+        self.offsets.push(self.data.len());
+    }
+
+    pub fn iter(&self) -> ActivesIter<'_> {
+        ActivesIter {
+            actives: self,
+            beam: 0,
+        }
+    }
+}
+
+impl Default for Actives {
+    fn default() -> Self {
+        Actives::new()
+    }
+}
+
+impl Index<usize> for Actives {
+    type Output = [usize];
+
+    fn index(&self, beam: usize) -> &Self::Output {
+        &self.data[self.offsets[beam]..self.offsets[beam + 1]]
+    }
+}
+
+impl fmt::Debug for Actives {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// Iterator over the beams of an actives table.
+pub struct ActivesIter<'a> {
+    actives: &'a Actives,
+    beam: usize,
+}
+
+impl<'a> Iterator for ActivesIter<'a> {
+    type Item = &'a [usize];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let active = self.actives.get(self.beam)?;
+        self.beam += 1;
+        Some(active)
+    }
+}
+
+impl<'a> IntoIterator for &'a Actives {
+    type Item = &'a [usize];
+    type IntoIter = ActivesIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Internal: serialized form of an actives table, checked before it becomes one.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct ActivesParts {
+    offsets: Vec<usize>,
+    data: Vec<usize>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<ActivesParts> for Actives {
+    type Error = String;
+
+    fn try_from(parts: ActivesParts) -> Result<Self, Self::Error> {
+        let ActivesParts { offsets, data } = parts;
+        if offsets.first() != Some(&0) || offsets.last() != Some(&data.len()) {
+            return Err("actives offsets must run from 0 to the data length".to_string());
+        }
+        if offsets.windows(2).any(|w| w[0] > w[1]) {
+            return Err("actives offsets must not decrease".to_string());
+        }
+        Ok(Actives { offsets, data })
+    }
+}
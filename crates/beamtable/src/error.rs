@@ -0,0 +1,71 @@
+use std::fmt;
+
+/// Errors from building and querying a BeamTable.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BeamTableError {
+    /// The segment at this index has a NaN or infinite coordinate.
+    NonFiniteSegment(usize),
+
+    /// A query point has a NaN or infinite coordinate.
+    NonFinitePoint,
+
+    /// The table has not been built.
+    NotBuilt,
+
+    /// The table has no beams, the geometry contained no lines.
+    Empty,
+
+    /// The mask does not have one entry per gap of every beam of the table.
+    MaskMismatch,
+
+    /// The sweep reached the end of the segment at this index without it being active.
+    InconsistentSweep(usize),
+
+    /// The flattening tolerance is not a finite number greater than zero.
+    InvalidTolerance(f64),
+
+    /// The snapping tolerance is not a finite number greater than zero.
+    InvalidSnap(f64),
+
+    /// The hatch spacing is not a finite number greater than zero.
+    InvalidSpacing(f64),
+
+    /// The hatch inset is NaN or infinite.
+    NonFiniteInset(f64),
+
+    /// The snap rounding grid is not finite and greater than zero, or too fine for the
+    /// coordinates of the geometry.
+    InvalidGrid(f64),
+}
+
+impl fmt::Display for BeamTableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BeamTableError::NonFiniteSegment(index) => {
+                write!(f, "segment {} has a non-finite coordinate", index)
+            }
+            BeamTableError::NonFinitePoint => write!(f, "point has a non-finite coordinate"),
+            BeamTableError::NotBuilt => write!(f, "beam table has not been built"),
+            BeamTableError::Empty => write!(f, "beam table has no beams"),
+            BeamTableError::MaskMismatch => write!(f, "mask does not match the beam table"),
+            BeamTableError::InconsistentSweep(index) => {
+                write!(f, "segment {} ended without being active in the sweep", index)
+            }
+            BeamTableError::InvalidTolerance(tolerance) => {
+                write!(f, "tolerance {} is not a finite number greater than zero", tolerance)
+            }
+            BeamTableError::InvalidSnap(snap) => {
+                write!(f, "snap {} is not a finite number greater than zero", snap)
+            }
+            BeamTableError::InvalidSpacing(spacing) => {
+                write!(f, "spacing {} is not a finite number greater than zero", spacing)
+            }
+            BeamTableError::NonFiniteInset(inset) => write!(f, "inset {} is not finite", inset),
+            BeamTableError::InvalidGrid(grid) => {
+                write!(f, "grid {} is not valid for the geometry", grid)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BeamTableError {}
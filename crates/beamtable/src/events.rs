@@ -2,7 +2,9 @@ use std::cmp::Ordering;
 
 use crate::geometry::Point;
 
-/// Events for the scantable build. Each event is sorted by x, y, and slope in that order.
+/// Events for the scantable build, ordered by their point: by x, then by y for points on the
+/// same scanline, x within the tolerance of the table. Segments starting, ending or swapping at
+/// the same point share one event.
 /// Ordering is done in *reversed* order to make the BinaryHeap structure give a minheap.
 #[derive(Debug, Clone)]
 pub struct Event {
@@ -10,42 +12,49 @@ pub struct Event {
     pub add: Vec<usize>,
     pub remove: Vec<usize>,
     pub update: Vec<usize>,
+    pub tolerance: f64,
 }
 
 impl Event {
-    pub fn from_pt(x: f64, y: f64) -> Event {
-        Event {
-            point: Point::new(x, y),
-            add: Vec::new(),
-            remove: Vec::new(),
-            update: Vec::new(),
-        }
-    }
-
-    pub fn swap(pt: Point, s1: usize, s2: usize) -> Event {
+    pub fn swap(pt: Point, s1: usize, s2: usize, tolerance: f64) -> Event {
         Event {
             point: pt,
             add: Vec::new(),
             remove: Vec::new(),
             update: vec![s1, s2],
+            tolerance,
         }
     }
-    pub fn start(pt: Point, start: usize) -> Event {
+    pub fn start(pt: Point, start: usize, tolerance: f64) -> Event {
         Event {
             point: pt,
             add: vec![start],
             remove: Vec::new(),
             update: Vec::new(),
+            tolerance,
         }
     }
-    pub fn end(pt: Point, end: usize) -> Event {
+    pub fn end(pt: Point, end: usize, tolerance: f64) -> Event {
         Event {
             point: pt,
             add: Vec::new(),
             remove: vec![end],
             update: Vec::new(),
+            tolerance,
         }
     }
+
+    /// Whether the other event occurs at the same place, within tolerance.
+    pub fn coincident(&self, other: &Event) -> bool {
+        self.point.eq_within(&other.point, self.tolerance)
+    }
+
+    /// Merge the adds, removes and updates of another event into this one.
+    pub fn merge(&mut self, other: Event) {
+        self.add.extend(other.add);
+        self.remove.extend(other.remove);
+        self.update.extend(other.update);
+    }
 }
 
 impl Eq for Event {}
@@ -64,7 +73,7 @@ impl PartialOrd<Self> for Event {
 
 impl Ord for Event {
     fn cmp(&self, other: &Self) -> Ordering {
-        match Point::cmp(&self.point, &other.point) {
+        match self.point.cmp_within(&other.point, self.tolerance) {
             Ordering::Greater => {
                 return Ordering::Less;
             }
@@ -1,6 +1,12 @@
 use std::cmp::Ordering;
+use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, TAU};
+
+use crate::matrix::Matrix;
+use crate::predicates::{orient2d, EPSILON};
+use crate::segment::{Segment, SegmentKind, SegmentTuple};
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Point {
     pub x: f64,
     pub y: f64,
@@ -11,6 +17,23 @@ impl Point {
     pub fn new(x: f64, y: f64) -> Point {
         Point { x, y }
     }
+
+    /// Sweep order with x values within tolerance on the same scanline, ordered by y. Equal if
+    /// y is also within tolerance.
+    pub fn cmp_within(&self, other: &Point, tolerance: f64) -> Ordering {
+        if (self.x - other.x).abs() < tolerance {
+            if (self.y - other.y).abs() < tolerance {
+                return Ordering::Equal;
+            }
+            return f64::total_cmp(&self.y, &other.y);
+        }
+        f64::total_cmp(&self.x, &other.x)
+    }
+
+    /// Whether both coordinates are within tolerance of the other point.
+    pub fn eq_within(&self, other: &Point, tolerance: f64) -> bool {
+        (self.x - other.x).abs() < tolerance && (self.y - other.y).abs() < tolerance
+    }
 }
 
 impl From<(f64, f64)> for Point {
@@ -23,7 +46,7 @@ impl Eq for Point {}
 
 impl PartialEq<Self> for Point {
     fn eq(&self, other: &Self) -> bool {
-        return (self.x - other.x).abs() < 1e-12 && (self.y - other.y).abs() < 1e-12;
+        self.eq_within(other, EPSILON)
     }
 }
 
@@ -35,22 +58,19 @@ impl PartialOrd<Self> for Point {
 
 impl Ord for Point {
     fn cmp(&self, other: &Self) -> Ordering {
-        let x_eq = (self.x - other.x).abs() < 1e-12;
-        if x_eq {
-            if (self.y - other.y).abs() < 1e-12 {
-                return Ordering::Equal;
-            }
-            return f64::partial_cmp(&self.y, &other.y).expect("No NaNs");
-        }
-        f64::partial_cmp(&self.x, &other.x).expect("No NaNs")
+        self.cmp_within(other, EPSILON)
     }
 }
 
+/// Most lines a single curve is flattened into.
+pub const MAX_FLATTEN_STEPS: usize = 1 << 16;
+
 /// Geomstr: Geometry class see, sister structure:
 /// https://github.com/meerk40t/meerk40t/blob/main/meerk40t/tools/geomstr.py
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Geomstr {
-    pub segments: Vec<((f64, f64), (f64, f64), (f64, f64), (f64, f64), (f64, f64))>,
+    pub segments: Vec<Segment>,
 }
 
 impl Geomstr {
@@ -59,12 +79,22 @@ impl Geomstr {
             segments: Vec::new(),
         }
     }
-    pub fn from_segments(
-        segments: Vec<((f64, f64), (f64, f64), (f64, f64), (f64, f64), (f64, f64))>,
-    ) -> Geomstr {
+    pub fn from_segments(segments: Vec<Segment>) -> Geomstr {
         Geomstr { segments }
     }
 
+    /// Create geometry from raw geomstr tuples.
+    pub fn from_tuples(segments: Vec<SegmentTuple>) -> Geomstr {
+        Geomstr {
+            segments: segments.into_iter().map(Segment::from).collect(),
+        }
+    }
+
+    /// Raw geomstr tuples for the geometry.
+    pub fn to_tuples(&self) -> Vec<SegmentTuple> {
+        self.segments.iter().map(|&s| s.into()).collect()
+    }
+
     /// Add a rectangle to the geometry.
     pub fn rect(&mut self, x: f64, y: f64, width: f64, height: f64, settings: f64) {
         self.line((x, y), (x + width, y), settings);
@@ -75,67 +105,458 @@ impl Geomstr {
 
     /// Add a line to the geometry.
     pub fn line(&mut self, p0: (f64, f64), p1: (f64, f64), settings: f64) {
-        self.segments
-            .push((p0, (0., 0.), (41.0, settings), (0., 0.), p1));
+        self.segments.push(Segment::line(p0, p1, settings));
+    }
+
+    /// Add a quadratic bezier to the geometry.
+    pub fn quad(&mut self, p0: (f64, f64), c: (f64, f64), p1: (f64, f64), settings: f64) {
+        self.segments.push(Segment::quad(p0, c, p1, settings));
+    }
+
+    /// Add a cubic bezier to the geometry.
+    pub fn cubic(
+        &mut self,
+        p0: (f64, f64),
+        c0: (f64, f64),
+        c1: (f64, f64),
+        p1: (f64, f64),
+        settings: f64,
+    ) {
+        self.segments.push(Segment::cubic(p0, c0, c1, p1, settings));
+    }
+
+    /// Add a circular arc from p0 through control to p1.
+    pub fn arc(&mut self, p0: (f64, f64), control: (f64, f64), p1: (f64, f64), settings: f64) {
+        self.segments.push(Segment::arc(p0, control, p1, settings));
+    }
+
+    /// Add a subpath separator to the geometry.
+    pub fn end(&mut self) {
+        self.segments.push(Segment::end_path());
+    }
+
+    /// Apply the transform to every point slot of every segment. Arcs stay arcs under
+    /// transforms that keep circles circular, otherwise each arc becomes cubic beziers, one per
+    /// 45 degrees of sweep, within 1e-5 of the radius of the arc.
+    pub fn transform(&mut self, matrix: &Matrix) {
+        let similar = matrix.is_similarity();
+        let mut segments = Vec::with_capacity(self.segments.len());
+        for (i, segment) in self.segments.iter().enumerate() {
+            if segment.kind() == SegmentKind::Arc && !similar {
+                if let Some(arc) = self.arc_geometry(i) {
+                    for mut cubic in arc_cubics(segment, arc) {
+                        cubic.transform(matrix);
+                        segments.push(cubic);
+                    }
+                    continue;
+                }
+            }
+            let mut segment = *segment;
+            segment.transform(matrix);
+            segments.push(segment);
+        }
+        self.segments = segments;
+    }
+
+    /// Whether the geometry contains any curved segments.
+    pub fn has_curves(&self) -> bool {
+        self.segments.iter().any(|s| s.kind().is_curve())
+    }
+
+    /// Returns a copy of the geometry with curves replaced by line segments that deviate from
+    /// the curve by no more than tolerance. Each curve gives at most MAX_FLATTEN_STEPS lines, a
+    /// tolerance that is zero, negative or NaN gives that many.
+    pub fn flatten(&self, tolerance: f64) -> Geomstr {
+        let tolerance = if tolerance > 0.0 { tolerance } else { 0.0 };
+        let mut g = Geomstr::new();
+        for (i, segment) in self.segments.iter().enumerate() {
+            let start = segment.start();
+            let end = segment.end();
+            let (c0, c1) = segment.controls();
+            let steps = match segment.kind() {
+                SegmentKind::Quad => {
+                    let dx = start.x - 2.0 * c0.x + end.x;
+                    let dy = start.y - 2.0 * c0.y + end.y;
+                    let dd = (dx * dx + dy * dy).sqrt();
+                    (dd / (4.0 * tolerance)).sqrt().ceil()
+                }
+                SegmentKind::Cubic => {
+                    let dx0 = start.x - 2.0 * c0.x + c1.x;
+                    let dy0 = start.y - 2.0 * c0.y + c1.y;
+                    let dx1 = c0.x - 2.0 * c1.x + end.x;
+                    let dy1 = c0.y - 2.0 * c1.y + end.y;
+                    let dd = f64::max(dx0 * dx0 + dy0 * dy0, dx1 * dx1 + dy1 * dy1).sqrt();
+                    (3.0 * dd / (4.0 * tolerance)).sqrt().ceil()
+                }
+                SegmentKind::Arc => match self.arc_geometry(i) {
+                    None => 1.0,
+                    Some((_, radius, _, sweep)) => {
+                        if tolerance >= radius {
+                            1.0
+                        } else {
+                            let step = 2.0 * (1.0 - tolerance / radius).acos();
+                            (sweep.abs() / step).ceil()
+                        }
+                    }
+                },
+                _ => {
+                    g.segments.push(*segment);
+                    continue;
+                }
+            };
+            let steps = steps.max(1.0).min(MAX_FLATTEN_STEPS as f64) as usize;
+            let settings = segment.settings();
+            let mut last = self.point(i, 0.0);
+            for k in 1..=steps {
+                let current = self.point(i, k as f64 / steps as f64);
+                g.line((last.x, last.y), (current.x, current.y), settings);
+                last = current;
+            }
+        }
+        g
+    }
+
+    /// Internal: center, radius, start angle and sweep of the arc at index. None if the points
+    /// are collinear.
+    fn arc_geometry(&self, index: usize) -> Option<((f64, f64), f64, f64, f64)> {
+        let arc = &self.segments[index];
+        let a = arc.start();
+        let b = arc.controls().0;
+        let c = arc.end();
+        let d = 2.0 * (a.x * (b.y - c.y) + b.x * (c.y - a.y) + c.x * (a.y - b.y));
+        if d.abs() < 1e-12 {
+            return None;
+        }
+        let a2 = a.x * a.x + a.y * a.y;
+        let b2 = b.x * b.x + b.y * b.y;
+        let c2 = c.x * c.x + c.y * c.y;
+        let ox = (a2 * (b.y - c.y) + b2 * (c.y - a.y) + c2 * (a.y - b.y)) / d;
+        let oy = (a2 * (c.x - b.x) + b2 * (a.x - c.x) + c2 * (b.x - a.x)) / d;
+        let radius = f64::hypot(a.x - ox, a.y - oy);
+        let tau = std::f64::consts::TAU;
+        let start = f64::atan2(a.y - oy, a.x - ox);
+        let control = (f64::atan2(b.y - oy, b.x - ox) - start).rem_euclid(tau);
+        let end = (f64::atan2(c.y - oy, c.x - ox) - start).rem_euclid(tau);
+        let sweep = if control <= end { end } else { end - tau };
+        Some(((ox, oy), radius, start, sweep))
     }
 
     /// Slope where divide by 0 is always negative infinity.
     pub fn slope(&self, index: usize) -> f64 {
         let line = &self.segments[index];
-        let rise: f64 = line.0 .1 - line.4 .1;
-        let run: f64 = line.0 .0 - line.4 .0;
+        let a = line.start();
+        let b = line.end();
+        let rise: f64 = a.y - b.y;
+        let run: f64 = a.x - b.x;
         if run == 0.0 {
             return f64::INFINITY;
         }
         rise / run
     }
 
+    /// Whether the segment runs along constant x, its ends within tolerance in x. Vertical
+    /// segments only occupy the zero width beams at their x.
+    pub fn is_vertical(&self, index: usize, tolerance: f64) -> bool {
+        let line = &self.segments[index];
+        (line.start().x - line.end().x).abs() < tolerance
+    }
+
+    /// Winding contribution of the segment crossing a beam, +1 left-to-right, -1 right-to-left.
+    /// Vertical segments, see `is_vertical`, cross no beam and contribute 0.
+    pub fn winding(&self, index: usize, tolerance: f64) -> i32 {
+        let line = &self.segments[index];
+        if self.is_vertical(index, tolerance) {
+            return 0;
+        }
+        if line.start().x < line.end().x {
+            return 1;
+        }
+        -1
+    }
+
     /// Find an intersection between index0 and index1.
+    /// Whether the segments meet is decided with exact orientation tests, collinear segments
+    /// return None. Returns the parameters t of the intersection along each segment.
     pub fn get_intersection(&self, index0: usize, index1: usize) -> Option<(f64, f64)> {
         let line0 = &self.segments[index0];
         let line1 = &self.segments[index1];
-        let a = &line0.0;
-        let b = &line0.4;
-        let c = &line1.0;
-        let d = &line1.4;
-        let denom: f64 = (d.1 - c.1) * (b.0 - a.0) - (d.0 - c.0) * (b.1 - a.1);
-        if denom.abs() < 1e-12 {
+        let a = line0.start();
+        let b = line0.end();
+        let c = line1.start();
+        let d = line1.end();
+        let o1 = orient2d(&a, &b, &c);
+        let o2 = orient2d(&a, &b, &d);
+        if o1 == 0.0 && o2 == 0.0 {
             return None;
         }
-        let t1: f64 = ((d.0 - c.0) * (a.1 - c.1) - (d.1 - c.1) * (a.0 - c.0)) / denom;
-        let t2: f64 = ((b.0 - a.0) * (a.1 - c.1) - (b.1 - a.1) * (a.0 - c.0)) / denom;
-        if 0.0 <= t1 && t1 <= 1.0 && 0.0 <= t2 && t2 <= 1.0 {
-            return Some((t1, t2));
+        if (o1 > 0.0 && o2 > 0.0) || (o1 < 0.0 && o2 < 0.0) {
+            return None;
         }
-        None
+        let o3 = orient2d(&c, &d, &a);
+        let o4 = orient2d(&c, &d, &b);
+        if (o3 > 0.0 && o4 > 0.0) || (o3 < 0.0 && o4 < 0.0) {
+            return None;
+        }
+        let t1: f64 = o3 / (o3 - o4);
+        let t2: f64 = o1 / (o1 - o2);
+        Some((t1, t2))
+    }
+
+    /// Whether the segments at index0 and index1 are collinear and share more than a point.
+    pub fn overlaps(&self, index0: usize, index1: usize) -> bool {
+        let line0 = &self.segments[index0];
+        let line1 = &self.segments[index1];
+        let a = line0.start();
+        let b = line0.end();
+        let c = line1.start();
+        let d = line1.end();
+        if orient2d(&a, &b, &c) != 0.0 || orient2d(&a, &b, &d) != 0.0 {
+            return false;
+        }
+        // Compare along the dominant axis.
+        let (a, b, c, d) = if (b.x - a.x).abs() >= (b.y - a.y).abs() {
+            (a.x, b.x, c.x, d.x)
+        } else {
+            (a.y, b.y, c.y, d.y)
+        };
+        a.max(b).min(c.max(d)) > a.min(b).max(c.min(d))
     }
 
     /// Returns the y_intercept point given a line a given x.
     /// Default is used for y if there is a line along the requested x.
     pub fn y_intercept(&self, index: usize, x: f64, default: f64) -> Point {
         let line = &self.segments[index];
-        let a = &line.0;
-        let b = &line.4;
-        let rise: f64 = a.1 - b.1;
-        let run: f64 = a.0 - b.0;
+        let a = line.start();
+        let b = line.end();
+        let rise: f64 = a.y - b.y;
+        let run: f64 = a.x - b.x;
         if rise == 0.0 {
-            return Point::new(x, a.1);
+            return Point::new(x, a.y);
         }
         if run == 0.0 {
             return Point::new(x, default);
         }
         let m = run / rise;
-        let x0: f64 = a.0 - (m * a.1);
+        let x0: f64 = a.x - (m * a.y);
         Point::new(x, (x - x0) / m)
     }
 
     /// Find point located within the current geometry at position t [0,1]
     pub fn point(&self, index: usize, t: f64) -> Point {
         let line = &self.segments[index];
-        Point::new(
-            t * (line.4 .0 - line.0 .0) + line.0 .0,
-            t * (line.4 .1 - line.0 .1) + line.0 .1,
-        )
+        let a = line.start();
+        let b = line.end();
+        let (c0, c1) = line.controls();
+        match line.kind() {
+            SegmentKind::Quad => {
+                let s = 1.0 - t;
+                Point::new(
+                    s * s * a.x + 2.0 * s * t * c0.x + t * t * b.x,
+                    s * s * a.y + 2.0 * s * t * c0.y + t * t * b.y,
+                )
+            }
+            SegmentKind::Cubic => {
+                let s = 1.0 - t;
+                Point::new(
+                    s * s * s * a.x + 3.0 * s * s * t * c0.x + 3.0 * s * t * t * c1.x + t * t * t * b.x,
+                    s * s * s * a.y + 3.0 * s * s * t * c0.y + 3.0 * s * t * t * c1.y + t * t * t * b.y,
+                )
+            }
+            SegmentKind::Arc if self.arc_geometry(index).is_some() => {
+                let (center, radius, start, sweep) = self.arc_geometry(index).unwrap();
+                let angle = start + t * sweep;
+                Point::new(
+                    center.0 + radius * angle.cos(),
+                    center.1 + radius * angle.sin(),
+                )
+            }
+            _ => Point::new(t * (b.x - a.x) + a.x, t * (b.y - a.y) + a.y),
+        }
+    }
+
+    /// Bounding box of the drawn segments, see `SegmentKind::is_drawn`, as (min_x, min_y, max_x,
+    /// max_y), None if there are none. Curves are bounded by their extreme points rather than their control points.
+    pub fn bbox(&self) -> Option<(f64, f64, f64, f64)> {
+        self.bbox_where(|_| true)
+    }
+
+    /// Bounding box of the drawn segments with the given settings value.
+    pub fn bbox_for_layer(&self, settings: f64) -> Option<(f64, f64, f64, f64)> {
+        self.bbox_where(|segment| segment.settings() == settings)
+    }
+
+    /// Internal: bounding box of the drawn segments that pass the filter.
+    fn bbox_where(&self, filter: impl Fn(&Segment) -> bool) -> Option<(f64, f64, f64, f64)> {
+        let mut bounds: Option<(f64, f64, f64, f64)> = None;
+        for (i, segment) in self.segments.iter().enumerate() {
+            if !segment.kind().is_drawn() || !filter(segment) {
+                continue;
+            }
+            for t in self.extrema(i) {
+                let p = self.point(i, t);
+                bounds = Some(match bounds {
+                    None => (p.x, p.y, p.x, p.y),
+                    Some((x0, y0, x1, y1)) => (x0.min(p.x), y0.min(p.y), x1.max(p.x), y1.max(p.y)),
+                });
+            }
+        }
+        bounds
+    }
+
+    /// Internal: parameters of the segment at index where x or y is extreme, ends included.
+    fn extrema(&self, index: usize) -> Vec<f64> {
+        let segment = &self.segments[index];
+        let (a, b) = (segment.start(), segment.end());
+        let (c0, c1) = segment.controls();
+        let mut ts = vec![0.0, 1.0];
+        match segment.kind() {
+            SegmentKind::Quad => {
+                for (p0, c, p1) in [(a.x, c0.x, b.x), (a.y, c0.y, b.y)] {
+                    let d = p0 - 2.0 * c + p1;
+                    if d != 0.0 {
+                        ts.push((p0 - c) / d);
+                    }
+                }
+            }
+            SegmentKind::Cubic => {
+                for (p0, p1, p2, p3) in [(a.x, c0.x, c1.x, b.x), (a.y, c0.y, c1.y, b.y)] {
+                    // Roots of the derivative, qa t^2 + qb t + qc.
+                    let qa = -p0 + 3.0 * p1 - 3.0 * p2 + p3;
+                    let qb = 2.0 * (p0 - 2.0 * p1 + p2);
+                    let qc = p1 - p0;
+                    if qa.abs() < 1e-12 {
+                        if qb != 0.0 {
+                            ts.push(-qc / qb);
+                        }
+                        continue;
+                    }
+                    let disc = qb * qb - 4.0 * qa * qc;
+                    if disc >= 0.0 {
+                        ts.push((-qb + disc.sqrt()) / (2.0 * qa));
+                        ts.push((-qb - disc.sqrt()) / (2.0 * qa));
+                    }
+                }
+            }
+            SegmentKind::Arc => {
+                if let Some((_, _, start, sweep)) = self.arc_geometry(index) {
+                    for k in 0..4 {
+                        let angle = k as f64 * FRAC_PI_2;
+                        let delta = if sweep > 0.0 {
+                            (angle - start).rem_euclid(TAU)
+                        } else {
+                            -(start - angle).rem_euclid(TAU)
+                        };
+                        ts.push(delta / sweep);
+                    }
+                }
+            }
+            _ => {}
+        }
+        ts.retain(|t| (0.0..=1.0).contains(t));
+        ts
+    }
+
+    /// Total length of the drawn segments, points counting as zero. Lines and arcs are exact,
+    /// beziers are measured along 64 chords.
+    pub fn length(&self) -> f64 {
+        let distance = |a: &Point, b: &Point| f64::hypot(b.x - a.x, b.y - a.y);
+        let mut total = 0.0;
+        for (i, segment) in self.segments.iter().enumerate() {
+            if !segment.kind().is_drawn() {
+                continue;
+            }
+            total += match segment.kind() {
+                SegmentKind::Line => distance(&segment.start(), &segment.end()),
+                SegmentKind::Arc => match self.arc_geometry(i) {
+                    Some((_, radius, _, sweep)) => radius * sweep.abs(),
+                    None => distance(&segment.start(), &segment.end()),
+                },
+                SegmentKind::Quad | SegmentKind::Cubic => {
+                    let mut last = segment.start();
+                    let mut length = 0.0;
+                    for k in 1..=64 {
+                        let current = self.point(i, k as f64 / 64.0);
+                        length += distance(&last, &current);
+                        last = current;
+                    }
+                    length
+                }
+                _ => 0.0,
+            };
+        }
+        total
+    }
+
+    /// Signed area enclosed by the closed subpaths, counter-clockwise (y-up) positive. A subpath
+    /// is a run of lines and curves each starting where the previous one ended, closed if it
+    /// ends where it started. Curves contribute their exact area.
+    pub fn area(&self) -> f64 {
+        let mut total = 0.0;
+        // First point, last point and area of the current subpath.
+        let mut run: Option<(Point, Point, f64)> = None;
+        for (i, segment) in self.segments.iter().enumerate() {
+            if !segment.kind().is_drawn() {
+                total += closed_area(run.take());
+                continue;
+            }
+            let part = self.area_part(i);
+            run = match run.take() {
+                Some((first, last, area)) if last == segment.start() => {
+                    Some((first, segment.end(), area + part))
+                }
+                previous => {
+                    total += closed_area(previous);
+                    Some((segment.start(), segment.end(), part))
+                }
+            };
+        }
+        total + closed_area(run)
+    }
+
+    /// Internal: the segment's part of the shoelace sum, the integral of (x dy - y dx) / 2.
+    /// Beziers use 3 point Gauss-Legendre quadrature, exact for their polynomial integrand.
+    fn area_part(&self, index: usize) -> f64 {
+        let segment = &self.segments[index];
+        let (a, b) = (segment.start(), segment.end());
+        let chord = (a.x * b.y - b.x * a.y) / 2.0;
+        let (c0, c1) = segment.controls();
+        let derivative = |t: f64| -> (f64, f64) {
+            let s = 1.0 - t;
+            match segment.kind() {
+                SegmentKind::Quad => (
+                    2.0 * s * (c0.x - a.x) + 2.0 * t * (b.x - c0.x),
+                    2.0 * s * (c0.y - a.y) + 2.0 * t * (b.y - c0.y),
+                ),
+                _ => (
+                    3.0 * s * s * (c0.x - a.x)
+                        + 6.0 * s * t * (c1.x - c0.x)
+                        + 3.0 * t * t * (b.x - c1.x),
+                    3.0 * s * s * (c0.y - a.y)
+                        + 6.0 * s * t * (c1.y - c0.y)
+                        + 3.0 * t * t * (b.y - c1.y),
+                ),
+            }
+        };
+        match segment.kind() {
+            SegmentKind::Quad | SegmentKind::Cubic => {
+                let offset = 0.5 * (0.6f64).sqrt();
+                [(0.5 - offset, 5.0), (0.5, 8.0), (0.5 + offset, 5.0)]
+                    .iter()
+                    .map(|&(t, weight)| {
+                        let p = self.point(index, t);
+                        let (dx, dy) = derivative(t);
+                        weight / 18.0 * (p.x * dy - p.y * dx) / 2.0
+                    })
+                    .sum()
+            }
+            SegmentKind::Arc => match self.arc_geometry(index) {
+                Some((_, radius, _, sweep)) => {
+                    chord + radius * radius / 2.0 * (sweep - sweep.sin())
+                }
+                None => chord,
+            },
+            _ => chord,
+        }
     }
 
     ///Check overall string distances
@@ -144,14 +565,14 @@ impl Geomstr {
         for i in 1..self.segments.len() {
             let line1 = &self.segments[i-1];
             let line2 = &self.segments[i];
-            if line1.2.0 != 41.0 {
+            if line1.kind() != SegmentKind::Line {
                 continue;
             }
-            if line2.2.0 != 41.0 {
+            if line2.kind() != SegmentKind::Line {
                 continue;
             }
-            let dx = line1.4.0 - line2.0.0;
-            let dy = line1.4.1 - line2.0.1;
+            let dx = line1.end().x - line2.start().x;
+            let dy = line1.end().y - line2.start().y;
             let delta = dx * dx + dy * dy;
             total += delta
         }
@@ -159,8 +580,7 @@ impl Geomstr {
     }
 
     pub fn reverse(&mut self, element: usize) {
-        let g = &self.segments[element];
-        self.segments[element] = ((g.4.0, g.4.1), (g.3.0, g.3.1), (g.2.0, g.2.1), (g.1.0, g.1.1), (g.0.0, g.0.1))
+        self.segments[element] = self.segments[element].reversed();
     }
 
     /// Perform greedy optimization to minimize travel distances
@@ -170,15 +590,15 @@ impl Geomstr {
             let mut best_k = usize::MAX;
             let mut best_flip = false;
             if j > 0 {
-                pt.x = self.segments[j-1].4.0;
-                pt.y = self.segments[j-1].4.1;
+                pt = self.segments[j-1].end();
             }
 
             for k in (j+1)..self.segments.len() {
                 let kline = &self.segments[k-1];
                 if flips {
-                    let dx = pt.x - kline.4.0;
-                    let dy = pt.y - kline.4.1;
+                    let end = kline.end();
+                    let dx = pt.x - end.x;
+                    let dy = pt.y - end.y;
                     let delta = dx * dx + dy * dy;
                     if delta < best {
                         best = delta;
@@ -186,8 +606,9 @@ impl Geomstr {
                         best_flip = true;
                     }
                 }
-                let dx = pt.x - kline.0.0;
-                let dy = pt.y - kline.0.1;
+                let start = kline.start();
+                let dx = pt.x - start.x;
+                let dy = pt.y - start.y;
                 let delta = dx * dx + dy * dy;
                 if delta < best {
                     best = delta;
@@ -202,3 +623,36 @@ impl Geomstr {
         }
     }
 }
+
+/// Internal: area of a subpath if it is closed, else 0.
+fn closed_area(run: Option<(Point, Point, f64)>) -> f64 {
+    match run {
+        Some((first, last, area)) if first == last => area,
+        _ => 0.0,
+    }
+}
+
+/// Internal: cubic beziers through the arc given its center, radius, start angle and sweep, one
+/// per 45 degrees of sweep. The ends are the arc's own ends.
+fn arc_cubics(
+    arc: &Segment,
+    (center, radius, start, sweep): ((f64, f64), f64, f64, f64),
+) -> Vec<Segment> {
+    let pieces = (sweep.abs() / FRAC_PI_4).ceil().max(1.0) as usize;
+    let step = sweep / pieces as f64;
+    let k = 4.0 / 3.0 * (step / 4.0).tan();
+    let at = |t: f64| (center.0 + radius * t.cos(), center.1 + radius * t.sin());
+    let tangent = |t: f64| (-radius * t.sin(), radius * t.cos());
+    let (first, last) = (arc.start(), arc.end());
+    (0..pieces)
+        .map(|i| {
+            let (t0, t1) = (start + step * i as f64, start + step * (i + 1) as f64);
+            let p0 = if i == 0 { (first.x, first.y) } else { at(t0) };
+            let p1 = if i + 1 == pieces { (last.x, last.y) } else { at(t1) };
+            let (d0, d1) = (tangent(t0), tangent(t1));
+            let c0 = (p0.0 + k * d0.0, p0.1 + k * d0.1);
+            let c1 = (p1.0 - k * d1.0, p1.1 - k * d1.1);
+            Segment::cubic(p0, c0, c1, p1, arc.settings())
+        })
+        .collect()
+}
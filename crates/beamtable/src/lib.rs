@@ -1,9 +1,27 @@
+mod actives;
+mod error;
 mod events;
 mod geometry;
+mod matrix;
+#[cfg(feature = "serde")]
+mod persist;
+mod predicates;
+mod segment;
+mod snap;
+mod status;
+#[cfg(feature = "svg")]
+mod svg;
 mod table;
 mod tests;
 
 // re-publish everything that's public in the sub-modules
+pub use actives::*;
+pub use error::*;
 pub use events::*;
 pub use geometry::*;
+pub use matrix::*;
+pub use predicates::*;
+pub use segment::*;
+#[cfg(feature = "svg")]
+pub use svg::*;
 pub use table::*;
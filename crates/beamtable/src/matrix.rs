@@ -0,0 +1,99 @@
+/// 2D affine transform in SVG order, mapping (x, y) to (a x + c y + e, b x + d y + f).
+/// Angles are in radians.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Matrix {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub d: f64,
+    pub e: f64,
+    pub f: f64,
+}
+
+impl Matrix {
+    pub fn new(a: f64, b: f64, c: f64, d: f64, e: f64, f: f64) -> Matrix {
+        Matrix { a, b, c, d, e, f }
+    }
+
+    pub fn identity() -> Matrix {
+        Matrix::new(1.0, 0.0, 0.0, 1.0, 0.0, 0.0)
+    }
+
+    pub fn translate(tx: f64, ty: f64) -> Matrix {
+        Matrix::new(1.0, 0.0, 0.0, 1.0, tx, ty)
+    }
+
+    pub fn scale(sx: f64, sy: f64) -> Matrix {
+        Matrix::new(sx, 0.0, 0.0, sy, 0.0, 0.0)
+    }
+
+    /// Rotation around the origin, from the x axis towards the y axis.
+    pub fn rotate(angle: f64) -> Matrix {
+        let (sin, cos) = angle.sin_cos();
+        Matrix::new(cos, sin, -sin, cos, 0.0, 0.0)
+    }
+
+    /// Skew by angle_x along x and angle_y along y, as SVG skewX and skewY.
+    pub fn skew(angle_x: f64, angle_y: f64) -> Matrix {
+        Matrix::new(1.0, angle_y.tan(), angle_x.tan(), 1.0, 0.0, 0.0)
+    }
+
+    /// This transform followed by other.
+    pub fn then(&self, other: &Matrix) -> Matrix {
+        Matrix::new(
+            other.a * self.a + other.c * self.b,
+            other.b * self.a + other.d * self.b,
+            other.a * self.c + other.c * self.d,
+            other.b * self.c + other.d * self.d,
+            other.a * self.e + other.c * self.f + other.e,
+            other.b * self.e + other.d * self.f + other.f,
+        )
+    }
+
+    pub fn determinant(&self) -> f64 {
+        self.a * self.d - self.b * self.c
+    }
+
+    /// Inverse transform, None if the transform collapses the plane.
+    pub fn inverse(&self) -> Option<Matrix> {
+        let det = self.determinant();
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+        Some(Matrix::new(
+            self.d / det,
+            -self.b / det,
+            -self.c / det,
+            self.a / det,
+            (self.c * self.f - self.d * self.e) / det,
+            (self.b * self.e - self.a * self.f) / det,
+        ))
+    }
+
+    /// Transformed point.
+    pub fn apply(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        (
+            self.a * x + self.c * y + self.e,
+            self.b * x + self.d * y + self.f,
+        )
+    }
+
+    /// Largest factor the transform stretches lengths by.
+    pub fn max_scale(&self) -> f64 {
+        f64::hypot(self.a, self.b).max(f64::hypot(self.c, self.d))
+    }
+
+    /// Whether the transform keeps circles circular: rotation, uniform scale, reflection and
+    /// translation only.
+    pub fn is_similarity(&self) -> bool {
+        let eps = 1e-9 * self.max_scale();
+        ((self.a - self.d).abs() <= eps && (self.b + self.c).abs() <= eps)
+            || ((self.a + self.d).abs() <= eps && (self.b - self.c).abs() <= eps)
+    }
+}
+
+impl Default for Matrix {
+    fn default() -> Self {
+        Matrix::identity()
+    }
+}
@@ -0,0 +1,49 @@
+//! Saving and loading tables, enabled with the `serde` feature. JSON is readable, MessagePack is
+//! the compact binary format for caching built tables.
+
+use crate::table::BeamTable;
+use serde::de::Error;
+
+impl BeamTable {
+    /// The table as JSON.
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+
+    /// Table from JSON written by `to_json`.
+    pub fn from_json(json: &str) -> Result<BeamTable, serde_json::Error> {
+        let table: BeamTable = serde_json::from_str(json)?;
+        table.check_loaded().map_err(serde_json::Error::custom)?;
+        Ok(table)
+    }
+
+    /// The table as MessagePack, with fields stored by position.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, rmp_serde::encode::Error> {
+        rmp_serde::to_vec(self)
+    }
+
+    /// Table from MessagePack written by `to_bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<BeamTable, rmp_serde::decode::Error> {
+        let table: BeamTable = rmp_serde::from_slice(bytes)?;
+        table
+            .check_loaded()
+            .map_err(rmp_serde::decode::Error::custom)?;
+        Ok(table)
+    }
+
+    /// Internal: whether a loaded table has one beam per event and only refers to segments of its
+    /// geometry.
+    fn check_loaded(&self) -> Result<(), &'static str> {
+        if self.events.len() != self.actives.len() {
+            return Err("table has a different number of events and beams");
+        }
+        let count = self.geometry.segments.len();
+        if self.actives.iter().flatten().any(|&active| active >= count) {
+            return Err("table refers to segments outside its geometry");
+        }
+        if self.overlaps.iter().any(|&(a, b)| a >= count || b >= count) {
+            return Err("table refers to segments outside its geometry");
+        }
+        Ok(())
+    }
+}
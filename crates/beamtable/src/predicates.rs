@@ -0,0 +1,97 @@
+//! Robust geometric predicates.
+//!
+//! Orientation tests are evaluated with a floating point filter and fall back to exact expansion
+//! arithmetic, after Shewchuk's "Adaptive Precision Floating-Point Arithmetic and Fast Robust
+//! Geometric Predicates". The signs returned are always exact.
+
+use crate::geometry::Point;
+
+/// Tolerance for treating coordinates as the same place. Used by the `Point` comparisons and as
+/// the default snapping tolerance of a BeamTable, the table's sweep and queries use its snap.
+pub const EPSILON: f64 = 1e-9;
+
+/// Error bound for the floating point orientation filter.
+const CCW_ERRBOUND: f64 = (3.0 + 16.0 * f64::EPSILON) * f64::EPSILON;
+
+/// Orientation of c relative to the directed line a to b. Positive if counter-clockwise (c is
+/// left of a to b), negative if clockwise and zero if collinear. The sign is exact.
+pub fn orient2d(a: &Point, b: &Point, c: &Point) -> f64 {
+    let detleft = (a.x - c.x) * (b.y - c.y);
+    let detright = (a.y - c.y) * (b.x - c.x);
+    let det = detleft - detright;
+    let detsum = detleft.abs() + detright.abs();
+    if det.abs() >= CCW_ERRBOUND * detsum {
+        return det;
+    }
+    // (a-c) x (b-c) expanded into exact products.
+    exact_sum(&[
+        (a.x, b.y, 1.0),
+        (a.x, c.y, -1.0),
+        (c.x, b.y, -1.0),
+        (a.y, b.x, -1.0),
+        (a.y, c.x, 1.0),
+        (c.y, b.x, 1.0),
+    ])
+}
+
+/// Sign of the cross product of the directions a0 to a1 and b0 to b1. Positive if b turns
+/// counter-clockwise from a. Exact.
+pub fn cross_sign(a0: &Point, a1: &Point, b0: &Point, b1: &Point) -> f64 {
+    // (a1-a0) x (b1-b0) expanded into exact products.
+    exact_sum(&[
+        (a1.x, b1.y, 1.0),
+        (a1.x, b0.y, -1.0),
+        (a0.x, b1.y, -1.0),
+        (a0.x, b0.y, 1.0),
+        (a1.y, b1.x, -1.0),
+        (a1.y, b0.x, 1.0),
+        (a0.y, b1.x, 1.0),
+        (a0.y, b0.x, -1.0),
+    ])
+}
+
+/// Internal: exact sum of sign * x * y terms, rounded to the nearest float.
+fn exact_sum(terms: &[(f64, f64, f64)]) -> f64 {
+    let mut expansion: Vec<f64> = Vec::with_capacity(terms.len() * 2);
+    for &(x, y, sign) in terms {
+        let (hi, lo) = two_product(sign * x, y);
+        grow_expansion(&mut expansion, lo);
+        grow_expansion(&mut expansion, hi);
+    }
+    // Components are nonoverlapping and increasing in magnitude, the largest carries the sign.
+    expansion.iter().rev().fold(0.0, |total, component| total + component)
+}
+
+/// Internal: add b to the expansion, keeping it nonoverlapping and free of zeros.
+fn grow_expansion(expansion: &mut Vec<f64>, b: f64) {
+    let mut q = b;
+    let mut out = 0;
+    for i in 0..expansion.len() {
+        let (sum, err) = two_sum(q, expansion[i]);
+        q = sum;
+        if err != 0.0 {
+            expansion[out] = err;
+            out += 1;
+        }
+    }
+    expansion.truncate(out);
+    if q != 0.0 {
+        expansion.push(q);
+    }
+}
+
+/// Internal: a + b as a float sum and its exact rounding error.
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let x = a + b;
+    let b_virtual = x - a;
+    let a_virtual = x - b_virtual;
+    let b_roundoff = b - b_virtual;
+    let a_roundoff = a - a_virtual;
+    (x, a_roundoff + b_roundoff)
+}
+
+/// Internal: a * b as a float product and its exact rounding error.
+fn two_product(a: f64, b: f64) -> (f64, f64) {
+    let x = a * b;
+    (x, a.mul_add(b, -x))
+}
@@ -0,0 +1,234 @@
+use crate::geometry::Point;
+use crate::matrix::Matrix;
+
+/// Segment type codes, stored in the info slot (`.2.0`) of each segment tuple, matching geomstr.
+pub const TYPE_NOP: f64 = 0.0;
+pub const TYPE_END: f64 = 2.0;
+pub const TYPE_MOVE: f64 = 16.0;
+pub const TYPE_POINT: f64 = 33.0;
+pub const TYPE_LINE: f64 = 41.0;
+pub const TYPE_ARC: f64 = 57.0;
+pub const TYPE_QUAD: f64 = 105.0;
+pub const TYPE_CUBIC: f64 = 121.0;
+
+/// Raw geomstr segment: start, control 0, (type, settings), control 1, end.
+pub type SegmentTuple = ((f64, f64), (f64, f64), (f64, f64), (f64, f64), (f64, f64));
+
+/// Kind of a segment. Codes which are not known are kept as `Other` so conversions are lossless.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SegmentKind {
+    Nop,
+    End,
+    Move,
+    Point,
+    Line,
+    Arc,
+    Quad,
+    Cubic,
+    Other(f64),
+}
+
+impl SegmentKind {
+    pub fn from_code(code: f64) -> SegmentKind {
+        match code {
+            TYPE_NOP => SegmentKind::Nop,
+            TYPE_END => SegmentKind::End,
+            TYPE_MOVE => SegmentKind::Move,
+            TYPE_POINT => SegmentKind::Point,
+            TYPE_LINE => SegmentKind::Line,
+            TYPE_ARC => SegmentKind::Arc,
+            TYPE_QUAD => SegmentKind::Quad,
+            TYPE_CUBIC => SegmentKind::Cubic,
+            _ => SegmentKind::Other(code),
+        }
+    }
+
+    pub fn code(&self) -> f64 {
+        match self {
+            SegmentKind::Nop => TYPE_NOP,
+            SegmentKind::End => TYPE_END,
+            SegmentKind::Move => TYPE_MOVE,
+            SegmentKind::Point => TYPE_POINT,
+            SegmentKind::Line => TYPE_LINE,
+            SegmentKind::Arc => TYPE_ARC,
+            SegmentKind::Quad => TYPE_QUAD,
+            SegmentKind::Cubic => TYPE_CUBIC,
+            SegmentKind::Other(code) => *code,
+        }
+    }
+
+    /// Whether the kind is a curve that must be flattened before a build.
+    pub fn is_curve(&self) -> bool {
+        matches!(self, SegmentKind::Arc | SegmentKind::Quad | SegmentKind::Cubic)
+    }
+
+    /// Whether the kind marks something drawn: points, lines and curves, not separators, moves
+    /// or unknown codes.
+    pub fn is_drawn(&self) -> bool {
+        matches!(
+            self,
+            SegmentKind::Point
+                | SegmentKind::Line
+                | SegmentKind::Arc
+                | SegmentKind::Quad
+                | SegmentKind::Cubic
+        )
+    }
+}
+
+/// Single geometry segment. Unused point slots are kept so the tuple form round-trips exactly.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "SegmentTuple", into = "SegmentTuple")
+)]
+pub struct Segment {
+    start: (f64, f64),
+    c0: (f64, f64),
+    kind: SegmentKind,
+    settings: f64,
+    c1: (f64, f64),
+    end: (f64, f64),
+}
+
+impl Segment {
+    pub fn new(
+        kind: SegmentKind,
+        start: (f64, f64),
+        c0: (f64, f64),
+        c1: (f64, f64),
+        end: (f64, f64),
+        settings: f64,
+    ) -> Segment {
+        Segment {
+            start,
+            c0,
+            kind,
+            settings,
+            c1,
+            end,
+        }
+    }
+
+    pub fn line(p0: (f64, f64), p1: (f64, f64), settings: f64) -> Segment {
+        Segment::new(SegmentKind::Line, p0, (0., 0.), (0., 0.), p1, settings)
+    }
+
+    /// Quadratic bezier. The control point is stored in both control slots.
+    pub fn quad(p0: (f64, f64), c: (f64, f64), p1: (f64, f64), settings: f64) -> Segment {
+        Segment::new(SegmentKind::Quad, p0, c, c, p1, settings)
+    }
+
+    pub fn cubic(
+        p0: (f64, f64),
+        c0: (f64, f64),
+        c1: (f64, f64),
+        p1: (f64, f64),
+        settings: f64,
+    ) -> Segment {
+        Segment::new(SegmentKind::Cubic, p0, c0, c1, p1, settings)
+    }
+
+    /// Circular arc from p0 through control to p1. The control point is stored in both control
+    /// slots.
+    pub fn arc(p0: (f64, f64), control: (f64, f64), p1: (f64, f64), settings: f64) -> Segment {
+        Segment::new(SegmentKind::Arc, p0, control, control, p1, settings)
+    }
+
+    /// Subpath separator.
+    pub fn end_path() -> Segment {
+        Segment::new(SegmentKind::End, (0., 0.), (0., 0.), (0., 0.), (0., 0.), 0.)
+    }
+
+    /// Move to a point without drawing.
+    pub fn move_to(p0: (f64, f64), p1: (f64, f64)) -> Segment {
+        Segment::new(SegmentKind::Move, p0, (0., 0.), (0., 0.), p1, 0.)
+    }
+
+    pub fn kind(&self) -> SegmentKind {
+        self.kind
+    }
+
+    pub fn start(&self) -> Point {
+        Point::new(self.start.0, self.start.1)
+    }
+
+    pub fn end(&self) -> Point {
+        Point::new(self.end.0, self.end.1)
+    }
+
+    /// Both control slots, for curves these are the control points.
+    pub fn controls(&self) -> (Point, Point) {
+        (
+            Point::new(self.c0.0, self.c0.1),
+            Point::new(self.c1.0, self.c1.1),
+        )
+    }
+
+    pub fn settings(&self) -> f64 {
+        self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: f64) {
+        self.settings = settings;
+    }
+
+    /// Whether the points the segment uses are all finite. Control points are only checked for
+    /// curves, separators have no points.
+    pub fn is_finite(&self) -> bool {
+        let finite = |p: (f64, f64)| p.0.is_finite() && p.1.is_finite();
+        match self.kind {
+            SegmentKind::Nop | SegmentKind::End => true,
+            SegmentKind::Quad | SegmentKind::Arc => {
+                finite(self.start) && finite(self.c0) && finite(self.end)
+            }
+            SegmentKind::Cubic => {
+                finite(self.start) && finite(self.c0) && finite(self.c1) && finite(self.end)
+            }
+            _ => finite(self.start) && finite(self.end),
+        }
+    }
+
+    /// Segment traversed in the opposite direction.
+    pub fn reversed(&self) -> Segment {
+        Segment::new(self.kind, self.end, self.c1, self.c0, self.start, self.settings)
+    }
+
+    /// Apply the transform to all four point slots, separators have no points and are left as
+    /// they are. Arcs stay exact only under transforms that keep circles circular.
+    pub fn transform(&mut self, matrix: &Matrix) {
+        if matches!(self.kind, SegmentKind::Nop | SegmentKind::End) {
+            return;
+        }
+        self.start = matrix.apply(self.start);
+        self.c0 = matrix.apply(self.c0);
+        self.c1 = matrix.apply(self.c1);
+        self.end = matrix.apply(self.end);
+    }
+}
+
+impl From<SegmentTuple> for Segment {
+    fn from(value: SegmentTuple) -> Self {
+        Segment::new(
+            SegmentKind::from_code(value.2 .0),
+            value.0,
+            value.1,
+            value.3,
+            value.4,
+            value.2 .1,
+        )
+    }
+}
+
+impl From<Segment> for SegmentTuple {
+    fn from(value: Segment) -> Self {
+        (
+            value.start,
+            value.c0,
+            (value.kind.code(), value.settings),
+            value.c1,
+            value.end,
+        )
+    }
+}
@@ -0,0 +1,140 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::geometry::{Geomstr, Point};
+use crate::segment::SegmentKind;
+
+/// Largest coordinate in grid steps, pixel indices stay exact and their differences fit in i64.
+pub(crate) const MAX_GRID_STEPS: f64 = (1u64 << 52) as f64;
+
+/// Snap round the lines of the geometry to a grid.
+///
+/// Hot pixels are the grid cells containing a line endpoint or an intersection. Each line is
+/// replaced by a polyline through the centers of every hot pixel it passes through, in order
+/// along the line. Afterwards every vertex is on the grid and lines only meet at grid points.
+/// The grid must be finite, greater than zero and no finer than MAX_GRID_STEPS across the
+/// coordinates, `BeamTable::build` checks this.
+pub(crate) fn snap_round(geometry: &Geomstr, intersections: &[Point], grid: f64) -> Geomstr {
+    let pixel = |pt: &Point| ((pt.x / grid).round() as i64, (pt.y / grid).round() as i64);
+
+    let mut hot: HashSet<(i64, i64)> = HashSet::new();
+    for segment in &geometry.segments {
+        if segment.kind() == SegmentKind::Line {
+            hot.insert(pixel(&segment.start()));
+            hot.insert(pixel(&segment.end()));
+        }
+    }
+    for pt in intersections {
+        hot.insert(pixel(pt));
+    }
+    if hot.is_empty() {
+        return geometry.clone();
+    }
+
+    // Bucket the hot pixels into square cells of m pixels so each line only visits the cells
+    // along it.
+    let (mut min_i, mut max_i, mut min_j, mut max_j) = (i64::MAX, i64::MIN, i64::MAX, i64::MIN);
+    for &(i, j) in &hot {
+        min_i = min_i.min(i);
+        max_i = max_i.max(i);
+        min_j = min_j.min(j);
+        max_j = max_j.max(j);
+    }
+    let area = (max_i - min_i + 1) as f64 * (max_j - min_j + 1) as f64;
+    let m = (area / hot.len().max(1) as f64).sqrt().ceil().max(1.0) as i64;
+    let mut buckets: HashMap<(i64, i64), Vec<(i64, i64)>> = HashMap::new();
+    for &(i, j) in &hot {
+        buckets
+            .entry((i.div_euclid(m), j.div_euclid(m)))
+            .or_default()
+            .push((i, j));
+    }
+
+    let mut g = Geomstr::new();
+    for segment in &geometry.segments {
+        if segment.kind() != SegmentKind::Line {
+            g.segments.push(*segment);
+            continue;
+        }
+        let a = segment.start();
+        let b = segment.end();
+        let (dx, dy) = (b.x - a.x, b.y - a.y);
+        let length_sq = dx * dx + dy * dy;
+
+        // Pixel rows covered by the line within each bucket column.
+        let i0 = pixel(&a).0.min(pixel(&b).0) - 1;
+        let i1 = pixel(&a).0.max(pixel(&b).0) + 1;
+        let mut passes: Vec<(f64, (i64, i64))> = Vec::new();
+        for column in i0.div_euclid(m)..=i1.div_euclid(m) {
+            let left = ((column * m) as f64 - 0.5) * grid;
+            let right = ((column * m + m) as f64 - 0.5) * grid;
+            let (y0, y1) = if dx == 0.0 {
+                (a.y, b.y)
+            } else {
+                let t0 = ((left - a.x) / dx).clamp(0.0, 1.0);
+                let t1 = ((right - a.x) / dx).clamp(0.0, 1.0);
+                (a.y + t0 * dy, a.y + t1 * dy)
+            };
+            let j0 = ((y0.min(y1) / grid).round() as i64 - 1).div_euclid(m);
+            let j1 = ((y0.max(y1) / grid).round() as i64 + 1).div_euclid(m);
+            for row in j0..=j1 {
+                let Some(pixels) = buckets.get(&(column, row)) else {
+                    continue;
+                };
+                for &(i, j) in pixels {
+                    let center = Point::new(i as f64 * grid, j as f64 * grid);
+                    if !crosses_square(&a, &b, &center, grid / 2.0) {
+                        continue;
+                    }
+                    let t = if length_sq == 0.0 {
+                        0.0
+                    } else {
+                        ((center.x - a.x) * dx + (center.y - a.y) * dy) / length_sq
+                    };
+                    passes.push((t, (i, j)));
+                }
+            }
+        }
+        passes.sort_by(|p, q| p.0.total_cmp(&q.0));
+        passes.dedup_by_key(|p| p.1);
+
+        let settings = segment.settings();
+        for pair in passes.windows(2) {
+            let (i0, j0) = pair[0].1;
+            let (i1, j1) = pair[1].1;
+            g.line(
+                (i0 as f64 * grid, j0 as f64 * grid),
+                (i1 as f64 * grid, j1 as f64 * grid),
+                settings,
+            );
+        }
+    }
+    g
+}
+
+/// Internal: whether the segment a to b touches the closed square around center.
+fn crosses_square(a: &Point, b: &Point, center: &Point, half: f64) -> bool {
+    // Liang-Barsky clip of the segment against the square.
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let mut t0: f64 = 0.0;
+    let mut t1: f64 = 1.0;
+    for (p, q) in [
+        (-dx, a.x - (center.x - half)),
+        (dx, (center.x + half) - a.x),
+        (-dy, a.y - (center.y - half)),
+        (dy, (center.y + half) - a.y),
+    ] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return false;
+            }
+        } else {
+            let r = q / p;
+            if p < 0.0 {
+                t0 = t0.max(r);
+            } else {
+                t1 = t1.min(r);
+            }
+        }
+    }
+    t0 <= t1
+}
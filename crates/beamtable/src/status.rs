@@ -0,0 +1,211 @@
+const NIL: usize = usize::MAX;
+
+#[derive(Debug, Clone)]
+struct Node {
+    left: usize,
+    right: usize,
+    parent: usize,
+    priority: u64,
+    present: bool,
+}
+
+/// Sweep status for the build. Active segments are kept in y-order in a treap whose nodes are
+/// indexed by segment, giving expected O(log n) insert, remove and neighbor lookups.
+/// The ordering is given by the caller at insertion time since it depends on the sweep position.
+#[derive(Debug, Clone)]
+pub(crate) struct SweepStatus {
+    nodes: Vec<Node>,
+    root: usize,
+    len: usize,
+    seed: u64,
+}
+
+impl SweepStatus {
+    /// Status able to hold segment indexes below capacity.
+    pub fn new(capacity: usize) -> SweepStatus {
+        SweepStatus {
+            nodes: vec![
+                Node {
+                    left: NIL,
+                    right: NIL,
+                    parent: NIL,
+                    priority: 0,
+                    present: false,
+                };
+                capacity
+            ],
+            root: NIL,
+            len: 0,
+            seed: 0x2545_F491_4F6C_DD1D,
+        }
+    }
+
+    pub fn contains(&self, segment: usize) -> bool {
+        self.nodes[segment].present
+    }
+
+    /// Insert the segment, below(other) must return whether segment goes below the other.
+    pub fn insert(&mut self, segment: usize, below: impl Fn(usize) -> bool) {
+        let mut parent = NIL;
+        let mut current = self.root;
+        let mut left = false;
+        while current != NIL {
+            parent = current;
+            left = below(current);
+            current = if left {
+                self.nodes[current].left
+            } else {
+                self.nodes[current].right
+            };
+        }
+        let priority = self.next_priority();
+        self.nodes[segment] = Node {
+            left: NIL,
+            right: NIL,
+            parent,
+            priority,
+            present: true,
+        };
+        if parent == NIL {
+            self.root = segment;
+        } else if left {
+            self.nodes[parent].left = segment;
+        } else {
+            self.nodes[parent].right = segment;
+        }
+        while self.nodes[segment].parent != NIL
+            && self.nodes[self.nodes[segment].parent].priority < priority
+        {
+            self.rotate_up(segment);
+        }
+        self.len += 1;
+    }
+
+    /// Remove the segment if present.
+    pub fn remove(&mut self, segment: usize) {
+        if !self.contains(segment) {
+            return;
+        }
+        loop {
+            let node = &self.nodes[segment];
+            let child = match (node.left, node.right) {
+                (NIL, NIL) => break,
+                (left, NIL) => left,
+                (NIL, right) => right,
+                (left, right) => {
+                    if self.nodes[left].priority > self.nodes[right].priority {
+                        left
+                    } else {
+                        right
+                    }
+                }
+            };
+            self.rotate_up(child);
+        }
+        let parent = self.nodes[segment].parent;
+        self.replace_child(parent, segment, NIL);
+        self.nodes[segment].parent = NIL;
+        self.nodes[segment].present = false;
+        self.len -= 1;
+    }
+
+    /// Segment directly below the given segment.
+    pub fn prev(&self, segment: usize) -> Option<usize> {
+        let mut current = segment;
+        if self.nodes[current].left != NIL {
+            current = self.nodes[current].left;
+            while self.nodes[current].right != NIL {
+                current = self.nodes[current].right;
+            }
+            return Some(current);
+        }
+        while self.nodes[current].parent != NIL {
+            let parent = self.nodes[current].parent;
+            if self.nodes[parent].right == current {
+                return Some(parent);
+            }
+            current = parent;
+        }
+        None
+    }
+
+    /// Segment directly above the given segment.
+    pub fn next(&self, segment: usize) -> Option<usize> {
+        let mut current = segment;
+        if self.nodes[current].right != NIL {
+            current = self.nodes[current].right;
+            while self.nodes[current].left != NIL {
+                current = self.nodes[current].left;
+            }
+            return Some(current);
+        }
+        while self.nodes[current].parent != NIL {
+            let parent = self.nodes[current].parent;
+            if self.nodes[parent].left == current {
+                return Some(parent);
+            }
+            current = parent;
+        }
+        None
+    }
+
+    /// Replace the contents of ordered with the segments from bottom to top.
+    pub fn write_ordered(&self, ordered: &mut Vec<usize>) {
+        ordered.clear();
+        ordered.reserve(self.len);
+        let mut stack = Vec::new();
+        let mut current = self.root;
+        while current != NIL || !stack.is_empty() {
+            while current != NIL {
+                stack.push(current);
+                current = self.nodes[current].left;
+            }
+            current = stack.pop().unwrap();
+            ordered.push(current);
+            current = self.nodes[current].right;
+        }
+    }
+
+    /// Internal: rotate node x above its parent.
+    fn rotate_up(&mut self, x: usize) {
+        let p = self.nodes[x].parent;
+        let g = self.nodes[p].parent;
+        if self.nodes[p].left == x {
+            let moved = self.nodes[x].right;
+            self.nodes[p].left = moved;
+            if moved != NIL {
+                self.nodes[moved].parent = p;
+            }
+            self.nodes[x].right = p;
+        } else {
+            let moved = self.nodes[x].left;
+            self.nodes[p].right = moved;
+            if moved != NIL {
+                self.nodes[moved].parent = p;
+            }
+            self.nodes[x].left = p;
+        }
+        self.nodes[p].parent = x;
+        self.nodes[x].parent = g;
+        self.replace_child(g, p, x);
+    }
+
+    /// Internal: point parent at new in place of old, parent NIL is the root.
+    fn replace_child(&mut self, parent: usize, old: usize, new: usize) {
+        if parent == NIL {
+            self.root = new;
+        } else if self.nodes[parent].left == old {
+            self.nodes[parent].left = new;
+        } else {
+            self.nodes[parent].right = new;
+        }
+    }
+
+    /// Internal: xorshift priorities, deterministic so builds are reproducible.
+    fn next_priority(&mut self) -> u64 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+        self.seed
+    }
+}
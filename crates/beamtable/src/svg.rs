@@ -0,0 +1,658 @@
+//! SVG import, enabled with the `svg` feature. Shapes keep their curves as native segments, each
+//! subpath ends with a separator, and the layer and fill rule of every shape go into the settings
+//! of its segments. Coordinates are in 96 dpi pixels: the viewBox and size of `svg` elements are
+//! applied along with transforms, absolute units on lengths are converted and relative units count
+//! as pixels.
+
+use crate::geometry::Geomstr;
+use crate::matrix::Matrix;
+use std::borrow::Cow;
+use std::f64::consts::{FRAC_PI_2, TAU};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use svgtypes::{Align, AspectRatio, Length, LengthUnit, PathParser, PathSegment, PointsParser};
+use svgtypes::{Transform, ViewBox};
+
+/// Most cubic pieces an elliptical arc is split into, whatever the tolerance.
+const MAX_ARC_PIECES: usize = 1024;
+
+/// Most `use` elements followed into each other, which stops reference cycles.
+const MAX_USE_DEPTH: usize = 16;
+
+/// Most `use` elements expanded in one document, which bounds the work for references that
+/// multiply at every level.
+pub const MAX_USES: usize = 10_000;
+
+/// SVG fill-rule of a shape.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FillRule {
+    NonZero,
+    EvenOdd,
+}
+
+impl FillRule {
+    fn parse(value: &str) -> Option<FillRule> {
+        match value.trim() {
+            "nonzero" => Some(FillRule::NonZero),
+            "evenodd" => Some(FillRule::EvenOdd),
+            _ => None,
+        }
+    }
+}
+
+/// Segment settings for a layer and fill rule: twice the layer, plus one for even-odd. Fill the
+/// settings value with `evenodd_fill` or `nonzero_fill` to match the fill rule.
+pub fn svg_settings(layer: usize, fill_rule: FillRule) -> f64 {
+    (2 * layer + (fill_rule == FillRule::EvenOdd) as usize) as f64
+}
+
+/// Layer and fill rule of settings made by `svg_settings`.
+pub fn svg_layer(settings: f64) -> (usize, FillRule) {
+    let value = settings as usize;
+    let fill_rule = if value % 2 == 1 {
+        FillRule::EvenOdd
+    } else {
+        FillRule::NonZero
+    };
+    (value / 2, fill_rule)
+}
+
+/// Geometry of a single SVG shape element.
+#[derive(Debug, Clone)]
+pub struct SvgPath {
+    /// Trailing number of the id of the nearest enclosing group with one, as in `layer1`, or 0.
+    pub layer: usize,
+
+    /// Fill rule of the shape, inherited from its groups.
+    pub fill_rule: FillRule,
+
+    /// Segments of the shape, with settings from `svg_settings`.
+    pub geometry: Geomstr,
+}
+
+/// Errors reading an SVG.
+#[derive(Debug)]
+pub enum SvgError {
+    /// The file could not be read.
+    Io(std::io::Error),
+
+    /// The text is not well-formed XML.
+    Xml(roxmltree::Error),
+
+    /// Expanding the `use` elements takes more than `MAX_USES` copies.
+    TooManyUses,
+}
+
+impl fmt::Display for SvgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SvgError::Io(err) => write!(f, "could not read svg: {}", err),
+            SvgError::Xml(err) => write!(f, "could not parse svg: {}", err),
+            SvgError::TooManyUses => {
+                write!(f, "svg expands more than {} use elements", MAX_USES)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SvgError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SvgError::Io(err) => Some(err),
+            SvgError::Xml(err) => Some(err),
+            SvgError::TooManyUses => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for SvgError {
+    fn from(err: std::io::Error) -> Self {
+        SvgError::Io(err)
+    }
+}
+
+impl From<roxmltree::Error> for SvgError {
+    fn from(err: roxmltree::Error) -> Self {
+        SvgError::Xml(err)
+    }
+}
+
+/// Shape elements of an SVG file in document order. An argument starting with `<` is read as the
+/// SVG text itself. Elliptical arcs, and circular arcs under non-uniform transforms, become cubic
+/// beziers deviating from the arc by no more than tolerance. Fails once more than `MAX_USES`
+/// `use` elements have been expanded.
+pub fn svg_paths(path_or_str: impl AsRef<Path>, tolerance: f64) -> Result<Vec<SvgPath>, SvgError> {
+    let source = path_or_str.as_ref();
+    let text = match source.to_str().filter(|s| s.trim_start().starts_with('<')) {
+        Some(text) => Cow::Borrowed(text),
+        None => Cow::Owned(std::fs::read_to_string(source)?),
+    };
+    let options = roxmltree::ParsingOptions {
+        allow_dtd: true,
+        ..Default::default()
+    };
+    let doc = roxmltree::Document::parse_with_options(&text, options)?;
+    let state = State {
+        transform: Matrix::identity(),
+        layer: 0,
+        fill_rule: FillRule::NonZero,
+        tolerance,
+        uses: 0,
+    };
+    let mut paths = Vec::new();
+    let mut uses = 0;
+    walk(doc.root_element(), state, &mut paths, &mut uses)?;
+    Ok(paths)
+}
+
+impl Geomstr {
+    /// Geometry of all shapes of an SVG file, or of SVG text when the argument starts with `<`.
+    /// See `svg_paths`, settings come from `svg_settings` with the layer and fill rule of each
+    /// shape.
+    pub fn from_svg(path_or_str: impl AsRef<Path>, tolerance: f64) -> Result<Geomstr, SvgError> {
+        let mut geometry = Geomstr::new();
+        for path in svg_paths(path_or_str, tolerance)? {
+            geometry.segments.extend(path.geometry.segments);
+        }
+        Ok(geometry)
+    }
+}
+
+/// Internal: inherited state while walking the document.
+#[derive(Clone, Copy)]
+struct State {
+    transform: Matrix,
+    layer: usize,
+    fill_rule: FillRule,
+    tolerance: f64,
+    uses: usize,
+}
+
+/// Internal: collect the shapes of node and its descendants, counting the `use` elements
+/// expanded in uses.
+fn walk(
+    node: roxmltree::Node,
+    mut state: State,
+    paths: &mut Vec<SvgPath>,
+    uses: &mut usize,
+) -> Result<(), SvgError> {
+    if !node.is_element() || property(node, "display") == Some("none") {
+        return Ok(());
+    }
+    if let Some(transform) = node.attribute("transform") {
+        if let Ok(t) = Transform::from_str(transform) {
+            let transform = Matrix::new(t.a, t.b, t.c, t.d, t.e, t.f);
+            state.transform = transform.then(&state.transform);
+        }
+    }
+    if let Some(fill_rule) = property(node, "fill-rule").and_then(FillRule::parse) {
+        state.fill_rule = fill_rule;
+    }
+
+    let name = node.tag_name().name();
+    if name == "svg" {
+        state.transform = viewport(node).then(&state.transform);
+    }
+    if name == "use" {
+        let Some(target) = href(node) else {
+            return Ok(());
+        };
+        if state.uses == MAX_USE_DEPTH {
+            return Ok(());
+        }
+        *uses += 1;
+        if *uses > MAX_USES {
+            return Err(SvgError::TooManyUses);
+        }
+        state.uses += 1;
+        let offset = Matrix::translate(length(node, "x"), length(node, "y"));
+        state.transform = offset.then(&state.transform);
+        if target.tag_name().name() == "symbol" {
+            for child in target.children() {
+                walk(child, state, paths, uses)?;
+            }
+            return Ok(());
+        }
+        return walk(target, state, paths, uses);
+    }
+    if matches!(name, "svg" | "g" | "a" | "switch") {
+        if name == "g" {
+            if let Some(layer) = node.attribute("id").and_then(layer_number) {
+                state.layer = layer;
+            }
+        }
+        for child in node.children() {
+            walk(child, state, paths, uses)?;
+        }
+        return Ok(());
+    }
+
+    let mut builder = Builder::new(&state);
+    match name {
+        "path" => builder.path(node.attribute("d").unwrap_or("")),
+        "rect" => {
+            let (x, y) = (length(node, "x"), length(node, "y"));
+            let (width, height) = (length(node, "width"), length(node, "height"));
+            let (rx, ry) = match (node.attribute("rx"), node.attribute("ry")) {
+                (None, None) => (0.0, 0.0),
+                (Some(_), None) => (length(node, "rx"), length(node, "rx")),
+                (None, Some(_)) => (length(node, "ry"), length(node, "ry")),
+                (Some(_), Some(_)) => (length(node, "rx"), length(node, "ry")),
+            };
+            builder.rect(
+                x,
+                y,
+                width,
+                height,
+                rx.min(width / 2.0),
+                ry.min(height / 2.0),
+            );
+        }
+        "circle" => {
+            let r = length(node, "r");
+            builder.ellipse(length(node, "cx"), length(node, "cy"), r, r);
+        }
+        "ellipse" => {
+            let (rx, ry) = (length(node, "rx"), length(node, "ry"));
+            builder.ellipse(length(node, "cx"), length(node, "cy"), rx, ry);
+        }
+        "line" => {
+            builder.move_to((length(node, "x1"), length(node, "y1")));
+            builder.line_to((length(node, "x2"), length(node, "y2")));
+        }
+        "polyline" | "polygon" => {
+            let mut points = PointsParser::from(node.attribute("points").unwrap_or(""));
+            if let Some(first) = points.next() {
+                builder.move_to(first);
+                points.for_each(|p| builder.line_to(p));
+                if name == "polygon" {
+                    builder.close();
+                }
+            }
+        }
+        _ => return Ok(()),
+    }
+    let geometry = builder.finish();
+    if !geometry.segments.is_empty() {
+        paths.push(SvgPath {
+            layer: state.layer,
+            fill_rule: state.fill_rule,
+            geometry,
+        });
+    }
+    Ok(())
+}
+
+/// Internal: presentation property from the style attribute, or else the attribute itself.
+fn property<'a>(node: roxmltree::Node<'a, '_>, name: &str) -> Option<&'a str> {
+    let style = node.attribute("style").and_then(|style| {
+        style
+            .split(';')
+            .filter_map(|declaration| declaration.split_once(':'))
+            .find(|(key, _)| key.trim() == name)
+            .map(|(_, value)| value.trim())
+    });
+    style.or_else(|| node.attribute(name))
+}
+
+/// Internal: length attribute in pixels, 0 if missing or invalid.
+fn length(node: roxmltree::Node, name: &str) -> f64 {
+    node.attribute(name)
+        .and_then(|value| Length::from_str(value).ok())
+        .map_or(0.0, |length| pixels(length).unwrap_or(length.number))
+}
+
+/// Internal: length in 96 dpi pixels, None for relative units.
+fn pixels(length: Length) -> Option<f64> {
+    let scale = match length.unit {
+        LengthUnit::None | LengthUnit::Px => 1.0,
+        LengthUnit::In => 96.0,
+        LengthUnit::Cm => 96.0 / 2.54,
+        LengthUnit::Mm => 96.0 / 25.4,
+        LengthUnit::Pt => 96.0 / 72.0,
+        LengthUnit::Pc => 16.0,
+        LengthUnit::Em | LengthUnit::Ex | LengthUnit::Percent => return None,
+    };
+    Some(length.number * scale)
+}
+
+/// Internal: transform from the user units of an `svg` element to those of its parent. The
+/// viewBox is fitted into the width and height as preserveAspectRatio says, a relative or missing
+/// size takes the size of the viewBox. Nested elements are moved to their x and y.
+fn viewport(node: roxmltree::Node) -> Matrix {
+    let nested = node.ancestors().skip(1).any(|n| n.has_tag_name("svg"));
+    let origin = if nested {
+        Matrix::translate(length(node, "x"), length(node, "y"))
+    } else {
+        Matrix::identity()
+    };
+    let view_box = node
+        .attribute("viewBox")
+        .and_then(|value| ViewBox::from_str(value).ok())
+        .filter(|vb| vb.w > 0.0 && vb.h > 0.0);
+    let Some(vb) = view_box else { return origin };
+    let size = |name: &str, default: f64| {
+        node.attribute(name)
+            .and_then(|value| Length::from_str(value).ok())
+            .and_then(pixels)
+            .filter(|size| *size > 0.0)
+            .unwrap_or(default)
+    };
+    let (width, height) = (size("width", vb.w), size("height", vb.h));
+    let aspect = node
+        .attribute("preserveAspectRatio")
+        .and_then(|value| AspectRatio::from_str(value).ok())
+        .unwrap_or_default();
+    let (mut sx, mut sy) = (width / vb.w, height / vb.h);
+    let (ax, ay) = match aspect.align {
+        Align::None | Align::XMinYMin => (0.0, 0.0),
+        Align::XMidYMin => (0.5, 0.0),
+        Align::XMaxYMin => (1.0, 0.0),
+        Align::XMinYMid => (0.0, 0.5),
+        Align::XMidYMid => (0.5, 0.5),
+        Align::XMaxYMid => (1.0, 0.5),
+        Align::XMinYMax => (0.0, 1.0),
+        Align::XMidYMax => (0.5, 1.0),
+        Align::XMaxYMax => (1.0, 1.0),
+    };
+    if aspect.align != Align::None {
+        let scale = if aspect.slice { sx.max(sy) } else { sx.min(sy) };
+        (sx, sy) = (scale, scale);
+    }
+    let tx = (width - vb.w * sx) * ax - vb.x * sx;
+    let ty = (height - vb.h * sy) * ay - vb.y * sy;
+    Matrix::new(sx, 0.0, 0.0, sy, tx, ty).then(&origin)
+}
+
+/// Internal: element a `use` refers to by its href or xlink:href.
+fn href<'a, 'input>(node: roxmltree::Node<'a, 'input>) -> Option<roxmltree::Node<'a, 'input>> {
+    let href = node
+        .attribute("href")
+        .or_else(|| node.attribute(("http://www.w3.org/1999/xlink", "href")))?;
+    let id = href.trim().strip_prefix('#')?;
+    node.document()
+        .descendants()
+        .find(|n| n.attribute("id") == Some(id))
+}
+
+/// Internal: trailing digits of a group id.
+fn layer_number(id: &str) -> Option<usize> {
+    let prefix = id.trim_end_matches(|c: char| c.is_ascii_digit());
+    id[prefix.len()..].parse().ok()
+}
+
+/// Internal: builds the geometry of one shape in untransformed coordinates, transforming points
+/// as segments are added.
+struct Builder {
+    transform: Matrix,
+    settings: f64,
+    tolerance: f64,
+    geometry: Geomstr,
+    start: (f64, f64),
+    current: (f64, f64),
+    drawn: bool,
+}
+
+impl Builder {
+    fn new(state: &State) -> Builder {
+        Builder {
+            transform: state.transform,
+            settings: svg_settings(state.layer, state.fill_rule),
+            tolerance: state.tolerance,
+            geometry: Geomstr::new(),
+            start: (0.0, 0.0),
+            current: (0.0, 0.0),
+            drawn: false,
+        }
+    }
+
+    fn point(&self, p: (f64, f64)) -> (f64, f64) {
+        self.transform.apply(p)
+    }
+
+    fn end_subpath(&mut self) {
+        if self.drawn {
+            self.geometry.end();
+            self.drawn = false;
+        }
+    }
+
+    fn move_to(&mut self, p: (f64, f64)) {
+        self.end_subpath();
+        self.start = p;
+        self.current = p;
+    }
+
+    fn line_to(&mut self, p: (f64, f64)) {
+        if p != self.current {
+            let (p0, p1) = (self.point(self.current), self.point(p));
+            self.geometry.line(p0, p1, self.settings);
+            self.drawn = true;
+        }
+        self.current = p;
+    }
+
+    fn quad_to(&mut self, c: (f64, f64), p: (f64, f64)) {
+        let (p0, c, p1) = (self.point(self.current), self.point(c), self.point(p));
+        self.geometry.quad(p0, c, p1, self.settings);
+        self.drawn = true;
+        self.current = p;
+    }
+
+    fn cubic_to(&mut self, c0: (f64, f64), c1: (f64, f64), p: (f64, f64)) {
+        let p0 = self.point(self.current);
+        let (c0, c1, p1) = (self.point(c0), self.point(c1), self.point(p));
+        self.geometry.cubic(p0, c0, c1, p1, self.settings);
+        self.drawn = true;
+        self.current = p;
+    }
+
+    /// SVG elliptical arc to p, converted to center parameterization as in the SVG
+    /// implementation notes. Circular arcs stay native arcs through their midpoint.
+    fn arc_to(
+        &mut self,
+        rx: f64,
+        ry: f64,
+        rotation: f64,
+        large_arc: bool,
+        sweep: bool,
+        p: (f64, f64),
+    ) {
+        let p0 = self.current;
+        if p == p0 {
+            return;
+        }
+        let (mut rx, mut ry) = (rx.abs(), ry.abs());
+        if rx == 0.0 || ry == 0.0 {
+            self.line_to(p);
+            return;
+        }
+        let (sin, cos) = rotation.to_radians().sin_cos();
+        let (hx, hy) = ((p0.0 - p.0) / 2.0, (p0.1 - p.1) / 2.0);
+        let x1 = cos * hx + sin * hy;
+        let y1 = -sin * hx + cos * hy;
+        let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+        if lambda > 1.0 {
+            rx *= lambda.sqrt();
+            ry *= lambda.sqrt();
+        }
+        let num = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+        let den = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+        let mut coef = (num / den).max(0.0).sqrt();
+        if large_arc == sweep {
+            coef = -coef;
+        }
+        let (cx1, cy1) = (coef * rx * y1 / ry, -coef * ry * x1 / rx);
+        let cx = cos * cx1 - sin * cy1 + (p0.0 + p.0) / 2.0;
+        let cy = sin * cx1 + cos * cy1 + (p0.1 + p.1) / 2.0;
+
+        let angle =
+            |ux: f64, uy: f64, vx: f64, vy: f64| f64::atan2(ux * vy - uy * vx, ux * vx + uy * vy);
+        let (ux, uy) = ((x1 - cx1) / rx, (y1 - cy1) / ry);
+        let (vx, vy) = ((-x1 - cx1) / rx, (-y1 - cy1) / ry);
+        let theta = angle(1.0, 0.0, ux, uy);
+        let mut delta = angle(ux, uy, vx, vy);
+        if !sweep && delta > 0.0 {
+            delta -= TAU;
+        } else if sweep && delta < 0.0 {
+            delta += TAU;
+        }
+
+        let at = |t: f64| {
+            let (x, y) = (rx * t.cos(), ry * t.sin());
+            (cx + cos * x - sin * y, cy + sin * x + cos * y)
+        };
+        let tangent = |t: f64| {
+            let (x, y) = (-rx * t.sin(), ry * t.cos());
+            (cos * x - sin * y, sin * x + cos * y)
+        };
+
+        if (rx - ry).abs() <= 1e-9 * rx && self.transform.is_similarity() {
+            let (p0, c, p1) = (
+                self.point(p0),
+                self.point(at(theta + delta / 2.0)),
+                self.point(p),
+            );
+            self.geometry.arc(p0, c, p1, self.settings);
+            self.drawn = true;
+            self.current = p;
+            return;
+        }
+
+        let radius = rx.max(ry) * self.transform.max_scale();
+        let mut pieces = (delta.abs() / FRAC_PI_2).ceil().max(1.0) as usize;
+        while pieces < MAX_ARC_PIECES {
+            let quarter = delta.abs() / pieces as f64 / 4.0;
+            let error = radius * 4.0 / 27.0 * quarter.sin().powi(6) / quarter.cos().powi(2);
+            if error <= self.tolerance {
+                break;
+            }
+            pieces *= 2;
+        }
+        let step = delta / pieces as f64;
+        let k = 4.0 / 3.0 * (step / 4.0).tan();
+        for i in 0..pieces {
+            let (t0, t1) = (theta + step * i as f64, theta + step * (i + 1) as f64);
+            let (a, b) = (at(t0), at(t1));
+            let (da, db) = (tangent(t0), tangent(t1));
+            let end = if i + 1 == pieces { p } else { b };
+            self.cubic_to(
+                (a.0 + k * da.0, a.1 + k * da.1),
+                (b.0 - k * db.0, b.1 - k * db.1),
+                end,
+            );
+        }
+    }
+
+    fn close(&mut self) {
+        self.line_to(self.start);
+        self.end_subpath();
+    }
+
+    /// Path data, stopping at the first error as SVG renderers do.
+    fn path(&mut self, data: &str) {
+        let mut cubic_control: Option<(f64, f64)> = None;
+        let mut quad_control: Option<(f64, f64)> = None;
+        for segment in PathParser::from(data) {
+            let Ok(segment) = segment else { break };
+            let (x0, y0) = self.current;
+            let at = |abs: bool, x: f64, y: f64| if abs { (x, y) } else { (x0 + x, y0 + y) };
+            let reflect =
+                |c: Option<(f64, f64)>| c.map_or((x0, y0), |c| (2.0 * x0 - c.0, 2.0 * y0 - c.1));
+            let (mut next_cubic, mut next_quad) = (None, None);
+            match segment {
+                PathSegment::MoveTo { abs, x, y } => self.move_to(at(abs, x, y)),
+                PathSegment::LineTo { abs, x, y } => self.line_to(at(abs, x, y)),
+                PathSegment::HorizontalLineTo { abs, x } => {
+                    self.line_to((if abs { x } else { x0 + x }, y0))
+                }
+                PathSegment::VerticalLineTo { abs, y } => {
+                    self.line_to((x0, if abs { y } else { y0 + y }))
+                }
+                PathSegment::CurveTo {
+                    abs,
+                    x1,
+                    y1,
+                    x2,
+                    y2,
+                    x,
+                    y,
+                } => {
+                    let c1 = at(abs, x2, y2);
+                    self.cubic_to(at(abs, x1, y1), c1, at(abs, x, y));
+                    next_cubic = Some(c1);
+                }
+                PathSegment::SmoothCurveTo { abs, x2, y2, x, y } => {
+                    let c1 = at(abs, x2, y2);
+                    self.cubic_to(reflect(cubic_control), c1, at(abs, x, y));
+                    next_cubic = Some(c1);
+                }
+                PathSegment::Quadratic { abs, x1, y1, x, y } => {
+                    let c = at(abs, x1, y1);
+                    self.quad_to(c, at(abs, x, y));
+                    next_quad = Some(c);
+                }
+                PathSegment::SmoothQuadratic { abs, x, y } => {
+                    let c = reflect(quad_control);
+                    self.quad_to(c, at(abs, x, y));
+                    next_quad = Some(c);
+                }
+                PathSegment::EllipticalArc {
+                    abs,
+                    rx,
+                    ry,
+                    x_axis_rotation,
+                    large_arc,
+                    sweep,
+                    x,
+                    y,
+                } => self.arc_to(rx, ry, x_axis_rotation, large_arc, sweep, at(abs, x, y)),
+                PathSegment::ClosePath { .. } => self.close(),
+            }
+            cubic_control = next_cubic;
+            quad_control = next_quad;
+        }
+    }
+
+    /// Rectangle, with corners rounded by rx and ry when both are positive.
+    fn rect(&mut self, x: f64, y: f64, width: f64, height: f64, rx: f64, ry: f64) {
+        if width <= 0.0 || height <= 0.0 {
+            return;
+        }
+        let (right, bottom) = (x + width, y + height);
+        if rx > 0.0 && ry > 0.0 {
+            self.move_to((x + rx, y));
+            self.line_to((right - rx, y));
+            self.arc_to(rx, ry, 0.0, false, true, (right, y + ry));
+            self.line_to((right, bottom - ry));
+            self.arc_to(rx, ry, 0.0, false, true, (right - rx, bottom));
+            self.line_to((x + rx, bottom));
+            self.arc_to(rx, ry, 0.0, false, true, (x, bottom - ry));
+            self.line_to((x, y + ry));
+            self.arc_to(rx, ry, 0.0, false, true, (x + rx, y));
+        } else {
+            self.move_to((x, y));
+            self.line_to((right, y));
+            self.line_to((right, bottom));
+            self.line_to((x, bottom));
+        }
+        self.close();
+    }
+
+    /// Ellipse, or circle when the radii are equal, as two half arcs.
+    fn ellipse(&mut self, cx: f64, cy: f64, rx: f64, ry: f64) {
+        if rx <= 0.0 || ry <= 0.0 {
+            return;
+        }
+        self.move_to((cx + rx, cy));
+        self.arc_to(rx, ry, 0.0, false, true, (cx - rx, cy));
+        self.arc_to(rx, ry, 0.0, false, true, (cx + rx, cy));
+        self.close();
+    }
+
+    fn finish(mut self) -> Geomstr {
+        self.end_subpath();
+        self.geometry
+    }
+}
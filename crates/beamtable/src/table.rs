@@ -1,10 +1,18 @@
+use crate::actives::Actives;
+use crate::error::BeamTableError;
 use crate::events::Event;
 use crate::geometry::{Geomstr, Point};
+use crate::matrix::Matrix;
+use crate::predicates::{cross_sign, orient2d, EPSILON};
+use crate::segment::SegmentKind;
+use crate::snap::{snap_round, MAX_GRID_STEPS};
+use crate::status::SweepStatus;
 use std::cmp::Ordering;
-use std::collections::HashMap;
-use std::ops::{BitAnd, BitOr, Not};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::ops::{BitAnd, BitOr, BitXor, Not, Sub};
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BoolOp {
     pub inside: Vec<Vec<bool>>,
 }
@@ -13,6 +21,19 @@ impl BoolOp {
     pub fn new(mask: Vec<Vec<bool>>) -> BoolOp {
         BoolOp { inside: mask }
     }
+
+    /// Whether the point x, y is within the filled space of the mask for the given table.
+    pub fn contains(&self, table: &BeamTable, x: f64, y: f64) -> Result<bool, BeamTableError> {
+        match table.locate(x, y)? {
+            Some((beam, gap)) => self
+                .inside
+                .get(beam)
+                .and_then(|gaps| gaps.get(gap))
+                .copied()
+                .ok_or(BeamTableError::MaskMismatch),
+            None => Ok(false),
+        }
+    }
 }
 
 impl BitAnd for BoolOp {
@@ -47,6 +68,39 @@ impl BitOr for BoolOp {
     }
 }
 
+impl BitXor for BoolOp {
+    type Output = Self;
+
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        let mut n = Vec::new();
+        for j in 0..rhs.inside.len() {
+            let mut m = Vec::new();
+            for k in 0..rhs.inside[j].len() {
+                m.push(self.inside[j][k] ^ rhs.inside[j][k]);
+            }
+            n.push(m);
+        }
+        Self::new(n)
+    }
+}
+
+/// Difference, space inside self but not inside rhs.
+impl Sub for BoolOp {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        let mut n = Vec::new();
+        for j in 0..rhs.inside.len() {
+            let mut m = Vec::new();
+            for k in 0..rhs.inside[j].len() {
+                m.push(self.inside[j][k] & !rhs.inside[j][k]);
+            }
+            n.push(m);
+        }
+        Self::new(n)
+    }
+}
+
 impl Not for BoolOp {
     type Output = Self;
 
@@ -63,27 +117,57 @@ impl Not for BoolOp {
     }
 }
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BeamTable {
     pub geometry: Geomstr,
     pub events: Vec<Point>,
-    pub actives: Vec<Vec<usize>>,
+    pub actives: Actives,
     pub intersections: Vec<Point>,
 
+    /// Pairs of collinear segments that overlap, sorted, lower index first.
+    pub overlaps: Vec<(usize, usize)>,
+
+    /// Flattening tolerance used when the geometry contains curves.
+    pub tolerance: f64,
+
+    /// Snapping tolerance, events, sweep positions and boundary points closer than this are
+    /// treated as the same. Events within snap in x are on the same scanline. The build fails
+    /// unless it is a finite number greater than zero.
+    pub snap: f64,
+
+    /// Grid spacing for snap rounding. If set, the build snap rounds the geometry to the grid.
+    pub grid: Option<f64>,
+
     built: bool,
 }
 
+/// Default tolerance for flattening curves during the build.
+pub const DEFAULT_TOLERANCE: f64 = 0.1;
+
 /// BeamTable acceleration structure. Creates a geometric space lookup table.
 impl BeamTable {
-    pub fn new(geometry: Geomstr) -> BeamTable {
+    /// New table for the geometry. With a grid, points and intersections are snap rounded to
+    /// multiples of the grid during the build. The grid must be finite and greater than zero,
+    /// the build fails otherwise.
+    pub fn new(geometry: Geomstr, grid: Option<f64>) -> BeamTable {
         BeamTable {
             geometry,
             events: Vec::new(),
-            actives: Vec::new(),
+            actives: Actives::new(),
             intersections: Vec::new(),
+            overlaps: Vec::new(),
+            tolerance: DEFAULT_TOLERANCE,
+            snap: EPSILON,
+            grid,
             built: false,
         }
     }
 
+    /// Whether the table has been built.
+    pub fn is_built(&self) -> bool {
+        self.built
+    }
+
     /// Create an Even/Odd fill for a given layer level.
     pub fn evenodd_fill(&self, layer: f64) -> BoolOp {
         let mut spacemask = Vec::new();
@@ -93,16 +177,70 @@ impl BeamTable {
             active_mask.push(inside);
             for a in active {
                 let line = &self.geometry.segments[*a as usize];
-                if line.2 .1 == layer {
+                if line.settings() == layer {
                     inside = !inside;
                 }
                 active_mask.push(inside);
             }
+            self.merge_overlaps(active, &mut active_mask);
             spacemask.push(active_mask);
         }
         BoolOp::new(spacemask)
     }
 
+    /// Create a Nonzero winding fill for a given layer level.
+    pub fn nonzero_fill(&self, layer: f64) -> BoolOp {
+        let mut spacemask = Vec::new();
+        for j in 0..self.actives.len() {
+            let mut active_mask = self.nonzero_beam(j, |a| {
+                if self.geometry.segments[a].settings() == layer {
+                    self.geometry.winding(a, self.snap)
+                } else {
+                    0
+                }
+            });
+            self.merge_overlaps(&self.actives[j], &mut active_mask);
+            spacemask.push(active_mask);
+        }
+        BoolOp::new(spacemask)
+    }
+
+    /// Create a Nonzero winding fill for all geometry.
+    pub fn nonzero_all(&self) -> BoolOp {
+        let mut spacemask = Vec::new();
+        for j in 0..self.actives.len() {
+            let mut active_mask = self.nonzero_beam(j, |a| self.geometry.winding(a, self.snap));
+            self.merge_overlaps(&self.actives[j], &mut active_mask);
+            spacemask.push(active_mask);
+        }
+        BoolOp::new(spacemask)
+    }
+
+    /// Internal: nonzero mask of beam j, with the winding of each active given by weight.
+    /// In a zero width beam the actives below the middle of the beam are those right of the
+    /// scanline and the actives above it those left of the scanline, so the gaps above the middle
+    /// are counted down from the top. Both ends of every beam stay empty.
+    fn nonzero_beam(&self, j: usize, weight: impl Fn(usize) -> i32) -> Vec<bool> {
+        let active = &self.actives[j];
+        let mut winding = vec![0; active.len() + 1];
+        for (k, &a) in active.iter().enumerate() {
+            winding[k + 1] = winding[k] + weight(a);
+        }
+        if let Some(right_event) = self.events.get(j + 1) {
+            let left_event = &self.events[j];
+            if (right_event.x - left_event.x).abs() < self.snap {
+                let y = (left_event.y + right_event.y) / 2.0;
+                let middle = active
+                    .partition_point(|&a| self.geometry.y_intercept(a, left_event.x, y).y < y);
+                winding[active.len()] = 0;
+                for k in (middle + 1..active.len()).rev() {
+                    winding[k] = winding[k + 1] - weight(active[k]);
+                }
+            }
+        }
+        winding.iter().map(|&w| w != 0).collect()
+    }
+
     /// Create an even_odd fill for all geometry.
     /// Useful for point in polygon solutions
     pub fn even_odd_ignoring_origin(&self) -> BoolOp {
@@ -115,6 +253,7 @@ impl BeamTable {
                 inside = !inside;
                 active_mask.push(inside);
             }
+            self.merge_overlaps(active, &mut active_mask);
             spacemask.push(active_mask);
         }
         BoolOp::new(spacemask)
@@ -129,159 +268,497 @@ impl BeamTable {
             active_mask.push(set.len() != 0);
             for a in active {
                 let line = &self.geometry.segments[*a as usize];
-                if set.contains_key(&(line.2 .1 as usize)) {
-                    set.remove(&(line.2 .1 as usize));
+                if set.contains_key(&(line.settings() as usize)) {
+                    set.remove(&(line.settings() as usize));
                 } else {
-                    set.insert(line.2 .1 as usize, true);
+                    set.insert(line.settings() as usize, true);
                 }
                 active_mask.push(set.len() != 0);
             }
+            self.merge_overlaps(active, &mut active_mask);
             spacemask.push(active_mask);
         }
         BoolOp::new(spacemask)
     }
 
-    /// Create geometry from a BoolOp.
-    pub fn create(&self, mask: BoolOp, greedy: bool) -> Geomstr {
+    /// Whether segments a and b are collinear and overlap.
+    pub fn is_overlap(&self, a: usize, b: usize) -> bool {
+        self.overlaps.binary_search(&(a.min(b), a.max(b))).is_ok()
+    }
+
+    /// Internal: overlapping edges are crossed together. The zero height gaps between
+    /// overlapping actives take the fill of the space above them, so the group of edges is a
+    /// single boundary if the fill changes across all of them, and no boundary otherwise.
+    fn merge_overlaps(&self, active: &[usize], mask: &mut [bool]) {
+        for k in (1..active.len()).rev() {
+            if self.is_overlap(active[k - 1], active[k]) {
+                mask[k] = mask[k + 1];
+            }
+        }
+    }
+
+    /// Create an intersection of layer a and layer b.
+    pub fn intersection(&self, a: f64, b: f64) -> BoolOp {
+        self.evenodd_fill(a) & self.evenodd_fill(b)
+    }
+
+    /// Create a difference of layer a minus layer b.
+    pub fn difference(&self, a: f64, b: f64) -> BoolOp {
+        self.evenodd_fill(a) - self.evenodd_fill(b)
+    }
+
+    /// Create an exclusive-or of layer a and layer b.
+    pub fn xor(&self, a: f64, b: f64) -> BoolOp {
+        self.evenodd_fill(a) ^ self.evenodd_fill(b)
+    }
+
+    /// Create geometry from a BoolOp. Each boundary line is oriented with the filled space on
+    /// its left.
+    pub fn create(&self, mask: BoolOp, greedy: bool) -> Result<Geomstr, BeamTableError> {
+        self.check_mask(&mask)?;
         let mut g = Geomstr::new();
+        for (start, end, index) in self.boundaries(&mask) {
+            let settings = self.geometry.segments[index].settings();
+            g.line((start.x, start.y), (end.x, end.y), settings);
+        }
+        if greedy {
+            g.greedy_distance(Point::new(0., 0.), false);
+        }
+        Ok(g)
+    }
+
+    /// Create closed rings from a BoolOp. Boundary pieces are oriented with the filled space on
+    /// their left, so outer boundaries are counter-clockwise and holes are clockwise (y-up), then
+    /// stitched end to start where they meet within snap. Collinear pieces are merged and each
+    /// ring is followed by an end.
+    pub fn create_polygons(&self, mask: BoolOp) -> Result<Geomstr, BeamTableError> {
+        self.check_mask(&mask)?;
+        let pieces = self.boundaries(&mask);
+
+        // Index the pieces by the snap sized cell of their start point, a point within snap of
+        // a start is in the same or a neighboring cell.
+        let cell = |pt: &Point| {
+            (
+                (pt.x / self.snap).floor() as i64,
+                (pt.y / self.snap).floor() as i64,
+            )
+        };
+        let mut starts: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+        for (i, piece) in pieces.iter().enumerate() {
+            starts.entry(cell(&piece.0)).or_default().push(i);
+        }
+        let find_start = |pt: &Point, used: &[bool]| -> Option<usize> {
+            let (i, j) = cell(pt);
+            for di in -1..=1 {
+                for dj in -1..=1 {
+                    let Some(candidates) = starts.get(&(i + di, j + dj)) else {
+                        continue;
+                    };
+                    let found = candidates
+                        .iter()
+                        .copied()
+                        .find(|&c| !used[c] && pieces[c].0.eq_within(pt, self.snap));
+                    if found.is_some() {
+                        return found;
+                    }
+                }
+            }
+            None
+        };
+
+        let mut g = Geomstr::new();
+        let mut used = vec![false; pieces.len()];
+        for first in 0..pieces.len() {
+            if used[first] {
+                continue;
+            }
+            // Walk the ring.
+            used[first] = true;
+            let mut ring = vec![first];
+            loop {
+                let current = &pieces[*ring.last().unwrap()];
+                match find_start(&current.1, &used) {
+                    Some(next) => {
+                        used[next] = true;
+                        ring.push(next);
+                    }
+                    None => break,
+                }
+            }
+
+            // Merge collinear pieces.
+            let mut merged: Vec<(Point, Point, usize)> = Vec::new();
+            for i in ring {
+                let piece = &pieces[i];
+                if let Some(last) = merged.last_mut() {
+                    let settings = self.geometry.segments[last.2].settings();
+                    if settings == self.geometry.segments[piece.2].settings()
+                        && collinear(&last.0, &last.1, &piece.1, self.snap)
+                    {
+                        last.1 = piece.1.clone();
+                        continue;
+                    }
+                }
+                merged.push(piece.clone());
+            }
+            if merged.len() > 1 {
+                let first = &merged[0];
+                let last = &merged[merged.len() - 1];
+                if self.geometry.segments[first.2].settings()
+                    == self.geometry.segments[last.2].settings()
+                    && collinear(&last.0, &first.0, &first.1, self.snap)
+                {
+                    let first = merged.remove(0);
+                    merged.last_mut().unwrap().1 = first.1;
+                }
+            }
+            for (start, end, index) in merged {
+                let settings = self.geometry.segments[index].settings();
+                g.line((start.x, start.y), (end.x, end.y), settings);
+            }
+            g.end();
+        }
+        Ok(g)
+    }
+
+    /// Internal: the table is built and the mask has one entry per gap of every beam.
+    fn check_mask(&self, mask: &BoolOp) -> Result<(), BeamTableError> {
+        if !self.built {
+            return Err(BeamTableError::NotBuilt);
+        }
+        if mask.inside.len() != self.actives.len() {
+            return Err(BeamTableError::MaskMismatch);
+        }
+        for (gaps, active) in mask.inside.iter().zip(&self.actives) {
+            if gaps.len() != active.len() + 1 {
+                return Err(BeamTableError::MaskMismatch);
+            }
+        }
+        Ok(())
+    }
+
+    /// Internal: boundary pieces of the mask as (start, end, segment), oriented with the filled
+    /// space on their left.
+    ///
+    /// A vertical segment is only active in the zero width beams at its x, each covering the
+    /// span between the beam's events. It is a boundary where the fill just left of it differs
+    /// from the fill just right of it, read from the neighboring beams. Overlapping verticals
+    /// give a single boundary. Other actives have no extent in a zero width beam.
+    fn boundaries(&self, mask: &BoolOp) -> Vec<(Point, Point, usize)> {
         let inside = &mask.inside;
-        for j in 0..inside.len() - 1 {
-            //mask exists at inside-1, but the final entry is actually pointless
+        let mut pieces: Vec<(Point, Point, usize)> = Vec::new();
+        for j in 0..inside.len().saturating_sub(1) {
             let left_event = &self.events[j];
             let beam_active = &self.actives[j];
             let right_event = &self.events[j + 1];
 
+            // Events within snap in x sort as the same x, the beam between them has no width.
+            if (right_event.x - left_event.x).abs() < self.snap {
+                let y = (left_event.y + right_event.y) / 2.0;
+                let mut previous: Option<usize> = None;
+                for &segment_active in beam_active {
+                    if !self.geometry.is_vertical(segment_active, self.snap) {
+                        continue;
+                    }
+                    let x = self.geometry.segments[segment_active].start().x;
+                    let grouped = previous.is_some_and(|p| self.is_overlap(p, segment_active));
+                    previous = Some(segment_active);
+                    if grouped {
+                        continue;
+                    }
+                    let left_space = self.side_fill(mask, x, y, false);
+                    let right_space = self.side_fill(mask, x, y, true);
+                    if left_space == right_space {
+                        continue;
+                    }
+                    let low = Point::new(x, left_event.y);
+                    let high = Point::new(x, right_event.y);
+                    if left_space {
+                        pieces.push((low, high, segment_active));
+                    } else {
+                        pieces.push((high, low, segment_active));
+                    }
+                }
+                continue;
+            }
+
             for k in 0..inside[j].len() - 1 {
                 let below_space = inside[j][k];
-                let segment_active = beam_active[k];
                 let above_space = inside[j][k + 1];
-                if (below_space && !above_space) || (!below_space && above_space) {
-                    //is a boundary.
-                    let start = self.geometry.y_intercept(
-                        segment_active as usize,
-                        left_event.x,
-                        left_event.y,
-                    );
-                    let end = self.geometry.y_intercept(
-                        segment_active as usize,
-                        right_event.x,
-                        right_event.y,
-                    );
-                    let line = &self.geometry.segments[segment_active as usize];
-                    g.line((start.x, start.y), (end.x, end.y), line.2 .1);
+                if below_space == above_space {
+                    continue;
+                }
+                let segment_active = beam_active[k];
+                let start = self.geometry.y_intercept(segment_active, left_event.x, left_event.y);
+                let end = self.geometry.y_intercept(segment_active, right_event.x, right_event.y);
+                if start.eq_within(&end, self.snap) {
+                    continue;
+                }
+                if above_space {
+                    pieces.push((start, end, segment_active));
+                } else {
+                    pieces.push((end, start, segment_active));
                 }
             }
         }
-        if greedy {
-            g.greedy_distance(Point::new(0., 0.), false);
-        }
-        g
+        pieces
     }
 
-    /// Find the actives for a particular x/y event space.
-    pub fn actives_at(&self, x: f64, y: f64) -> &Vec<usize> {
-        let idx = self.events.binary_search(&Point::new(x, y));
-        match idx {
-            Ok(value) => {
-                return &self.actives[value];
-            }
-            Err(value) => {
-                if value == 0 {
-                    return &self.actives.last().expect("at least 1 active must exist.");
+    /// Internal: fill of the mask at height y just left or just right of x. Events within
+    /// snap of x are on the scanline at x.
+    fn side_fill(&self, mask: &BoolOp, x: f64, y: f64, right: bool) -> bool {
+        let after = if right {
+            self.events.partition_point(|e| e.x < x + self.snap)
+        } else {
+            self.events.partition_point(|e| e.x <= x - self.snap)
+        };
+        let Some(beam) = after.checked_sub(1) else {
+            return false;
+        };
+        let gap = self.actives[beam]
+            .partition_point(|&a| self.geometry.y_intercept(a, x, y).y < y);
+        mask.inside[beam][gap]
+    }
+
+    /// Create parallel hatch lines spaced apart within the filled space of the mask.
+    /// At angle 0 the lines run along y at multiples of spacing in x, other angles (radians)
+    /// rotate the mask's boundary, build a new table and rotate the hatch back.
+    /// Bidirectional alternates the direction of successive lines, inset pulls the line ends
+    /// away from the edges. Spacing must be finite and greater than zero, inset finite.
+    pub fn hatch(
+        &self,
+        mask: &BoolOp,
+        spacing: f64,
+        angle: f64,
+        bidirectional: bool,
+        inset: f64,
+    ) -> Result<Geomstr, BeamTableError> {
+        self.check_mask(mask)?;
+        if !(spacing > 0.0 && spacing.is_finite()) {
+            return Err(BeamTableError::InvalidSpacing(spacing));
+        }
+        if !inset.is_finite() {
+            return Err(BeamTableError::NonFiniteInset(inset));
+        }
+        if angle != 0.0 {
+            let mut rings = self.create_polygons(mask.clone())?;
+            rings.transform(&Matrix::rotate(-angle));
+            let mut table = BeamTable::new(rings, self.grid);
+            table.snap = self.snap;
+            table.build()?;
+            let fill = table.nonzero_all();
+            let mut g = table.hatch(&fill, spacing, 0.0, bidirectional, inset)?;
+            g.transform(&Matrix::rotate(angle));
+            return Ok(g);
+        }
+        let mut g = Geomstr::new();
+        let (first, last) = match (self.events.first(), self.events.last()) {
+            (Some(first), Some(last)) => (first.x, last.x),
+            _ => return Ok(g),
+        };
+        let mut forward = true;
+        let mut k = (first / spacing).floor() as i64;
+        loop {
+            let x = k as f64 * spacing;
+            k += 1;
+            if x >= last {
+                break;
+            }
+            if x <= first {
+                continue;
+            }
+            let beam = self.events.partition_point(|e| e.x <= x) - 1;
+            let actives = &self.actives[beam];
+            let inside = &mask.inside[beam];
+            let mut lines = Vec::new();
+            let mut gap = 1;
+            while gap < actives.len() {
+                if !inside[gap] {
+                    gap += 1;
+                    continue;
+                }
+                // Filled gaps next to each other make one line, split only where the fill ends.
+                let below = actives[gap - 1];
+                while gap + 1 < actives.len() && inside[gap + 1] {
+                    gap += 1;
+                }
+                let above = actives[gap];
+                gap += 1;
+                let y0 = self.geometry.y_intercept(below, x, 0.0).y
+                    + inset * f64::hypot(1.0, self.geometry.slope(below));
+                let y1 = self.geometry.y_intercept(above, x, 0.0).y
+                    - inset * f64::hypot(1.0, self.geometry.slope(above));
+                if y0 < y1 {
+                    lines.push((y0, y1, self.geometry.segments[below].settings()));
+                }
+            }
+            if lines.is_empty() {
+                continue;
+            }
+            if bidirectional && !forward {
+                for (y0, y1, settings) in lines.into_iter().rev() {
+                    g.line((x, y1), (x, y0), settings);
+                }
+            } else {
+                for (y0, y1, settings) in lines {
+                    g.line((x, y0), (x, y1), settings);
                 }
-                let value = value.checked_sub(1).unwrap();
-                return &self.actives[value];
             }
+            forward = !forward;
         }
+        Ok(g)
     }
 
-    pub fn bisect_events(&self, pos: &Point, events: &Vec<Event>) -> i32 {
-        let mut hi: usize = events.len();
-        let mut lo: usize = 0;
-        while lo < hi {
-            let mid = (lo + hi) / 2;
-            let q = &events[mid];
-            let x = pos.x - q.point.x;
-            if x > 1e-8 {
-                // x is still greater
-                lo = mid + 1;
+    /// Area of the filled space of the mask, summing the trapezoids each filled gap spans between
+    /// the events of its beam. Infinite if the mask fills space outside all segments. Zero width
+    /// beams, between events within snap in x, cover no area and are skipped.
+    pub fn area(&self, mask: &BoolOp) -> Result<f64, BeamTableError> {
+        self.check_mask(mask)?;
+        let mut area = 0.0;
+        for (j, inside) in mask.inside.iter().enumerate() {
+            let actives = &self.actives[j];
+            // The last beam reaches past every event.
+            let x0 = self.events[j].x;
+            let x1 = self.events.get(j + 1).map_or(f64::INFINITY, |e| e.x);
+            if x1 - x0 < self.snap {
                 continue;
             }
-            if x < -1e-8 {
-                // x is now less than
-                hi = mid;
-                continue
+            if inside[0] || inside[actives.len()] {
+                return Ok(f64::INFINITY);
             }
-            // x is equal.
-            let y = pos.y - q.point.y;
-            if y > 1e-8 {
-                // y is still greater
-                lo = mid + 1;
-                continue;
-            }
-            if y < -1e-8 {
-                // y is now less than
-                hi = mid;
-                continue
+            for gap in 1..actives.len() {
+                if !inside[gap] {
+                    continue;
+                }
+                let below = actives[gap - 1];
+                let above = actives[gap];
+                let height = |x: f64| {
+                    self.geometry.y_intercept(above, x, 0.0).y
+                        - self.geometry.y_intercept(below, x, 0.0).y
+                };
+                area += (x1 - x0) * (height(x0) + height(x1)) / 2.0;
             }
-            // y is also equal.
-            return mid as i32;
         }
-        !lo as i32
+        Ok(area)
     }
 
-    /// Internal: find the position within the given actives for the current x.
-    fn bisect_yints(&self, actives: &Vec<usize>, x: usize, scanline: &Point) -> usize {
+    /// Find the actives for a particular x/y event space.
+    pub fn actives_at(&self, x: f64, y: f64) -> Result<&[usize], BeamTableError> {
+        if !self.built {
+            return Err(BeamTableError::NotBuilt);
+        }
+        if !x.is_finite() || !y.is_finite() {
+            return Err(BeamTableError::NonFinitePoint);
+        }
+        let pt = Point::new(x, y);
+        let idx = self.events.binary_search_by(|e| e.cmp_within(&pt, self.snap));
+        let active = match idx {
+            Ok(value) => self.actives.get(value),
+            Err(0) => self.actives.last(),
+            Err(value) => self.actives.get(value - 1),
+        };
+        active.ok_or(BeamTableError::Empty)
+    }
+
+    /// Find the beam index and the gap index within that beam for the point x, y.
+    /// Gap 0 is below every active, points on an active count as below it. Points within snap of
+    /// the x of an event, including on a vertical segment, are located in the beam to the right.
+    /// Returns None if x is before the first event.
+    pub fn locate(&self, x: f64, y: f64) -> Result<Option<(usize, usize)>, BeamTableError> {
+        if !self.built {
+            return Err(BeamTableError::NotBuilt);
+        }
+        if !x.is_finite() || !y.is_finite() {
+            return Err(BeamTableError::NonFinitePoint);
+        }
+        let after = self.events.partition_point(|e| e.x < x + self.snap);
+        let Some(beam) = after.checked_sub(1) else {
+            return Ok(None);
+        };
+        let gap = self.actives[beam]
+            .partition_point(|&a| self.geometry.y_intercept(a, x, y).y < y);
+        Ok(Some((beam, gap)))
+    }
+
+    /// Internal: whether segment value is below segment test at the scanline.
+    /// Segments meeting at the scanline, exactly or within the snap tolerance, are ordered by
+    /// their direction with an exact test.
+    fn below(&self, value: usize, test: usize, scanline: &Point) -> bool {
         let geometry = &self.geometry;
-        let mut lo = 0;
-        let mut hi = actives.len();
-        let mut mid;
-        while lo < hi {
-            mid = (lo + hi) / 2;
-            let test = &geometry.y_intercept(actives[mid], scanline.x, scanline.y);
-            let value = &geometry.y_intercept(x, scanline.x, scanline.y);
-            match Point::cmp(&value, &test) {
-                Ordering::Less => {
-                    hi = mid;
-                }
-                Ordering::Greater => {
-                    lo = mid + 1;
-                }
-                Ordering::Equal => {
-                    let test_slope = &geometry.slope(actives[mid]);
-                    let value_slope = &geometry.slope(x);
-                    if value_slope < test_slope {
-                        hi = mid
-                    } else {
-                        lo = mid + 1
-                    }
-                }
+        let (value_left, value_right) = self.endpoints(value);
+        let (test_left, test_right) = self.endpoints(test);
+        let value_on = orient2d(&value_left, &value_right, scanline) == 0.0;
+        let test_on = orient2d(&test_left, &test_right, scanline) == 0.0;
+        if !(value_on && test_on) {
+            let test_y = geometry.y_intercept(test, scanline.x, scanline.y).y;
+            let value_y = geometry.y_intercept(value, scanline.x, scanline.y).y;
+            if (value_y - test_y).abs() > self.snap {
+                return value_y < test_y;
             }
         }
-        lo
+        let cross = cross_sign(&value_left, &value_right, &test_left, &test_right);
+        if cross == 0.0 {
+            // Overlapping segments are kept in index order.
+            return value < test;
+        }
+        cross > 0.0
+    }
+
+    /// Internal: endpoints of the segment in sweep order.
+    fn endpoints(&self, index: usize) -> (Point, Point) {
+        let segment = &self.geometry.segments[index];
+        let (p0, p1) = (segment.start(), segment.end());
+        if (p0.x, p0.y) <= (p1.x, p1.y) {
+            (p0, p1)
+        } else {
+            (p1, p0)
+        }
     }
 
-    fn get_or_insert_event<'a>(&'a self, pt: &Point, events: &'a mut Vec<Event>) -> &mut Event {
-        let mut ip1 = self.bisect_events(pt, events);
-        if ip1 < 0 {
-            ip1 = !ip1;
-            events.insert(ip1 as usize, Event::from_pt(pt.x, pt.y));
+    /// Internal: insert segment into the sweep status and check it against its new neighbors.
+    fn insert_active(
+        &mut self,
+        events: &mut BinaryHeap<Event>,
+        status: &mut SweepStatus,
+        checked_swaps: &mut HashSet<(usize, usize)>,
+        segment: usize,
+        sl: &Point,
+    ) {
+        status.insert(segment, |other| self.below(segment, other, sl));
+        if let Some(prev) = status.prev(segment) {
+            self.check_intersections(events, checked_swaps, prev, segment, sl);
+        }
+        if let Some(next) = status.next(segment) {
+            self.check_intersections(events, checked_swaps, segment, next, sl);
         }
-        &mut events[ip1 as usize]
     }
 
-    /// Internal: check for intersections between indexes q and r, occurring after sl
+    /// Internal: remove segment from the sweep status and check the neighbors it separated.
+    fn remove_active(
+        &mut self,
+        events: &mut BinaryHeap<Event>,
+        status: &mut SweepStatus,
+        checked_swaps: &mut HashSet<(usize, usize)>,
+        segment: usize,
+        sl: &Point,
+    ) {
+        let prev = status.prev(segment);
+        let next = status.next(segment);
+        status.remove(segment);
+        if let (Some(prev), Some(next)) = (prev, next) {
+            self.check_intersections(events, checked_swaps, prev, next, sl);
+        }
+    }
+
+    /// Internal: check for intersections between segments q and r, occurring after sl
     fn check_intersections(
         &mut self,
-        events: &mut Vec<Event>,
-        actives: &Vec<usize>,
-        checked_swaps: &mut Vec<(usize, usize)>,
+        events: &mut BinaryHeap<Event>,
+        checked_swaps: &mut HashSet<(usize, usize)>,
         q: usize,
         r: usize,
         sl: &Point,
     ) {
-        let q = actives[q] as usize;
-        let r = actives[r] as usize;
         let geometry = &self.geometry;
         if checked_swaps.contains(&(q, r)) {
             return;
@@ -289,7 +766,11 @@ impl BeamTable {
         let intersection = geometry.get_intersection(q, r);
 
         match intersection {
-            None => (),
+            None => {
+                if geometry.overlaps(q, r) {
+                    self.overlaps.push((q.min(r), q.max(r)));
+                }
+            }
             Some(t) => {
                 let t1 = t.0;
                 let t2 = t.1;
@@ -298,7 +779,7 @@ impl BeamTable {
                 }
                 let pt_intersect = geometry.point(q, t1);
                 self.intersections.push(pt_intersect.clone());
-                match Point::cmp(&sl, &pt_intersect) {
+                match sl.cmp_within(&pt_intersect, self.snap) {
                     Ordering::Greater => {
                         return;
                     }
@@ -307,151 +788,131 @@ impl BeamTable {
                     }
                     Ordering::Less => {}
                 }
-                checked_swaps.push((q, r));
-                let event = self.get_or_insert_event(&pt_intersect, events);
-                event.update.push(q);
-                event.update.push(r);
+                checked_swaps.insert((q, r));
+                events.push(Event::swap(pt_intersect, q, r, self.snap));
             }
         }
     }
 
     /// Builds the beamtable from the underlying geometry.
-    pub fn build(&mut self) {
+    /// Curved segments are flattened to lines first, replacing the geometry. With a grid the
+    /// geometry is then replaced by its snap rounded lines.
+    /// Fails without changing the table if any segment has a non-finite coordinate, if the
+    /// tolerance, the snap or the grid is not a finite number greater than zero, or if the grid
+    /// is so fine that the coordinates span more than 2^52 grid steps. An inconsistent sweep
+    /// discards the partial table.
+    pub fn build(&mut self) -> Result<(), BeamTableError> {
         if self.built {
             //This was already built.
-            return;
+            return Ok(());
+        }
+        if let Some(index) = self.geometry.segments.iter().position(|s| !s.is_finite()) {
+            return Err(BeamTableError::NonFiniteSegment(index));
+        }
+        if !(self.tolerance > 0.0 && self.tolerance.is_finite()) {
+            return Err(BeamTableError::InvalidTolerance(self.tolerance));
+        }
+        if !(self.snap > 0.0 && self.snap.is_finite()) {
+            return Err(BeamTableError::InvalidSnap(self.snap));
+        }
+        if let Some(grid) = self.grid {
+            let extent = match self.geometry.bbox() {
+                Some((x0, y0, x1, y1)) => x0.abs().max(y0.abs()).max(x1.abs()).max(y1.abs()),
+                None => 0.0,
+            };
+            if !(grid > 0.0 && grid.is_finite()) || extent / grid > MAX_GRID_STEPS {
+                return Err(BeamTableError::InvalidGrid(grid));
+            }
+        }
+        if self.geometry.has_curves() {
+            self.geometry = self.geometry.flatten(self.tolerance);
+        }
+        if let Some(grid) = self.grid {
+            // First pass finds the intersections for the hot pixels.
+            let mut table = BeamTable::new(self.geometry.clone(), None);
+            table.snap = self.snap;
+            table.build()?;
+            self.geometry = snap_round(&self.geometry, &table.intersections, grid);
         }
-        let mut events: Vec<Event> = Vec::new();
-        let mut checked_swaps: Vec<(usize, usize)> = Vec::new();
-        let mut actives: Vec<usize> = Vec::new();
+        let mut events: BinaryHeap<Event> = BinaryHeap::new();
+        let mut checked_swaps: HashSet<(usize, usize)> = HashSet::new();
+        let mut status = SweepStatus::new(self.geometry.segments.len());
+        let mut ordered: Vec<usize> = Vec::new();
 
         // Create initial start and end values for the event queue.
         for i in 0..self.geometry.segments.len() {
             let line = &self.geometry.segments[i];
-            if line.2.0 != 41.0 { continue; } // Must be line type.
-            let p0 = Point::new(line.0 .0, line.0 .1);
-            let p1 = Point::new(line.4 .0, line.4 .1);
-            match Point::cmp(&p0, &p1) {
+            if line.kind() != SegmentKind::Line { continue; } // Must be line type.
+            let p0 = line.start();
+            let p1 = line.end();
+            match p0.cmp_within(&p1, self.snap) {
                 Ordering::Less => {
-                    let ev1 = self.get_or_insert_event(&p0, &mut events);
-                    ev1.add.push(i);
-                    let ev2 = self.get_or_insert_event(&p1, &mut events);
-                    ev2.remove.push(i);
+                    events.push(Event::start(p0, i, self.snap));
+                    events.push(Event::end(p1, i, self.snap));
                 }
                 _ => {
-                    let ev1 = self.get_or_insert_event(&p1, &mut events);
-                    ev1.add.push(i);
-                    let ev2 = self.get_or_insert_event(&p0, &mut events);
-                    ev2.remove.push(i);
+                    events.push(Event::start(p1, i, self.snap));
+                    events.push(Event::end(p0, i, self.snap));
                 }
             }
         }
 
         // Process the event queue, performs Bentley-Ottmann line intersection checks
-        // for i in 0..events.len() {
-        //     let event = &events[i];
-        //     print!("{:?}\n", event);
-        // }
-        while events.len() != 0 {
-            let event = events.remove(0);
+        while let Some(mut event) = events.pop() {
+            // Events at the same place are processed together.
+            while events.peek().is_some_and(|next| event.coincident(next)) {
+                event.merge(events.pop().unwrap());
+            }
 
             let pt = &event.point;
-            for a in 0..event.add.len() {
-                let ad = event.add[a];
+            for &ad in &event.add {
                 // Insert.
-                let ip = self.bisect_yints(&actives, ad, &event.point);
-                actives.insert(ip, ad);
-                if ip > 0 {
-                    self.check_intersections(
-                        &mut events,
-                        &actives,
-                        &mut checked_swaps,
-                        ip - 1,
-                        ip,
-                        pt,
-                    )
-                }
-                if ip < actives.len() - 1 {
-                    self.check_intersections(
-                        &mut events,
-                        &actives,
-                        &mut checked_swaps,
-                        ip,
-                        ip + 1,
-                        pt,
-                    )
-                }
+                self.insert_active(&mut events, &mut status, &mut checked_swaps, ad, pt);
             }
-            for r in 0..event.remove.len() {
-                let rm = event.remove[r];
+            for &rm in &event.remove {
                 //Remove.
-                let rp = actives
-                    .iter()
-                    .position(|&e| e == rm)
-                    .expect("Was added should remove");
-                actives.remove(rp);
-                if 0 < rp && rp < actives.len() {
-                    self.check_intersections(
-                        &mut events,
-                        &actives,
-                        &mut checked_swaps,
-                        rp - 1,
-                        rp,
-                        pt,
-                    )
+                if !status.contains(rm) {
+                    self.clear();
+                    return Err(BeamTableError::InconsistentSweep(rm));
                 }
+                self.remove_active(&mut events, &mut status, &mut checked_swaps, rm, pt);
             }
-            for u in 0..event.update.len() {
-                let ud = event.update[u];
-
+            for &ud in &event.update {
                 //Remove.
-                if !actives.contains(&ud) {
-                    continue
-                }
-                let rp = actives
-                    .iter()
-                    .position(|&e| e == ud)
-                    .expect("Was added should remove.");
-                actives.remove(rp);
-                if 0 < rp && rp < actives.len() {
-                    self.check_intersections(
-                        &mut events,
-                        &actives,
-                        &mut checked_swaps,
-                        rp - 1,
-                        rp,
-                        pt,
-                    )
+                if !status.contains(ud) {
+                    continue;
                 }
+                self.remove_active(&mut events, &mut status, &mut checked_swaps, ud, pt);
                 // readd.
-                let ip = self.bisect_yints(&actives, ud, &event.point) as usize;
-                actives.insert(ip, ud);
-                if ip > 0 {
-                    self.check_intersections(
-                        &mut events,
-                        &actives,
-                        &mut checked_swaps,
-                        ip - 1,
-                        ip,
-                        pt,
-                    )
-                }
-                if ip < actives.len() - 1 {
-                    self.check_intersections(
-                        &mut events,
-                        &actives,
-                        &mut checked_swaps,
-                        ip,
-                        ip + 1,
-                        pt,
-                    )
-                }
+                self.insert_active(&mut events, &mut status, &mut checked_swaps, ud, pt);
             }
 
             // Push the current state to the table
             self.events.push((*pt).clone());
-            self.actives.push(actives.clone());
+            status.write_ordered(&mut ordered);
+            self.actives.push(&ordered);
         }
+        self.overlaps.sort();
+        self.overlaps.dedup();
         self.built = true;
+        Ok(())
+    }
+
+    /// Internal: drop the partial results of a failed build.
+    fn clear(&mut self) {
+        self.events.clear();
+        self.actives = Actives::new();
+        self.intersections.clear();
+        self.overlaps.clear();
     }
 }
+
+/// Internal: whether b lies within tolerance of the line from a to c, continuing in the same
+/// direction.
+fn collinear(a: &Point, b: &Point, c: &Point, tolerance: f64) -> bool {
+    let (dx0, dy0) = (b.x - a.x, b.y - a.y);
+    let (dx1, dy1) = (c.x - b.x, c.y - b.y);
+    // Twice the area of the triangle over the length of its base, the distance of b from a to c.
+    let cross = dx0 * dy1 - dy0 * dx1;
+    cross.abs() <= tolerance * f64::hypot(c.x - a.x, c.y - a.y) && dx0 * dx1 + dy0 * dy1 > 0.0
+}
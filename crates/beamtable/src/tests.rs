@@ -1,7 +1,13 @@
 #[cfg(test)]
 mod tests {
-    use crate::geometry::Geomstr;
-    use crate::table::BeamTable;
+    use crate::actives::Actives;
+    use crate::error::BeamTableError;
+    use crate::geometry::{Geomstr, Point, MAX_FLATTEN_STEPS};
+    use crate::matrix::Matrix;
+    use crate::predicates::orient2d;
+    use crate::segment::{Segment, SegmentKind, SegmentTuple};
+    use crate::status::SweepStatus;
+    use crate::table::{BeamTable, BoolOp};
     use rand::prelude::ThreadRng;
     use rand::Rng;
 
@@ -10,8 +16,8 @@ mod tests {
         let mut segments = Geomstr::new();
         segments.rect(0.0, 0.0, 100.0, 100.0, 1.0);
         segments.rect(5.0, 5.0, 105.0, 105.0, 0.0);
-        let mut table = BeamTable::new(segments);
-        table.build();
+        let mut table = BeamTable::new(segments, None);
+        table.build().unwrap();
         println!("{:?}", table.actives);
         println!("{:?}", table.events);
     }
@@ -31,18 +37,18 @@ mod tests {
                 }
             }
         }
-        let mut table = BeamTable::new(segments);
-        table.build();
+        let mut table = BeamTable::new(segments, None);
+        table.build().unwrap();
         // println!("{:?}", table.actives);
         // println!("{:?}", table.events);
         for x in 0..1000 {
             let x = x as f64;
-            let actives = table.actives_at(x, 0.0);
+            let actives = table.actives_at(x, 0.0).unwrap();
             for i in 1..actives.len() {
                 let prev = &table.geometry.segments[actives[i - 1] as usize];
                 let line = &table.geometry.segments[actives[i] as usize];
-                let pp0 = prev.0 .0;
-                let pp1 = prev.4 .0;
+                let pp0 = prev.start().x;
+                let pp1 = prev.end().x;
                 if pp0 < pp1 {
                     // println!("{pp0:?} {pp1:?} for {x:?}");
                     assert!(x >= pp0);
@@ -52,8 +58,8 @@ mod tests {
                     assert!(x >= pp1);
                     assert!(x <= pp0);
                 }
-                let cp0 = line.0 .0;
-                let cp1 = line.4 .0;
+                let cp0 = line.start().x;
+                let cp1 = line.end().x;
                 if cp0 < cp1 {
                     // println!("{cp0:?} {cp1:?} for {x:?}");
                     assert!(x >= cp0);
@@ -79,18 +85,18 @@ mod tests {
         segments.line((746.0, 867.0), (680.0, 52.0), 9.0);
         segments.line((961.0, 481.0), (662.0, 182.0), 7.0);
 
-        let mut table = BeamTable::new(segments);
-        table.build();
+        let mut table = BeamTable::new(segments, None);
+        table.build().unwrap();
         println!("{:?}", table.actives);
         println!("{:?}", table.events);
         for x in 689..690 {
             let x = x as f64;
-            let actives = table.actives_at(x, 0.0);
+            let actives = table.actives_at(x, 0.0).unwrap();
             for i in 1..actives.len() {
                 let prev = &table.geometry.segments[actives[i - 1] as usize];
                 let line = &table.geometry.segments[actives[i] as usize];
-                let pp0 = prev.0 .0;
-                let pp1 = prev.4 .0;
+                let pp0 = prev.start().x;
+                let pp1 = prev.end().x;
                 if pp0 < pp1 {
                     println!("{pp0:?} {pp1:?} for {x:?}");
                     assert!(x >= pp0);
@@ -100,8 +106,8 @@ mod tests {
                     assert!(x >= pp1);
                     assert!(x <= pp0);
                 }
-                let cp0 = line.0 .0;
-                let cp1 = line.4 .0;
+                let cp0 = line.start().x;
+                let cp1 = line.end().x;
                 if cp0 < cp1 {
                     println!("{cp0:?} {cp1:?} for {x:?}");
                     assert!(x >= cp0);
@@ -118,4 +124,836 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn nonzero_nested_rects() {
+        let mut segments = Geomstr::new();
+        segments.rect(0.0, 0.0, 100.0, 100.0, 1.0);
+        segments.rect(25.0, 25.0, 50.0, 50.0, 1.0);
+        let mut table = BeamTable::new(segments, None);
+        table.build().unwrap();
+        let beam = table.events.iter().position(|e| e.x > 50.0).unwrap() - 1;
+        let evenodd = table.evenodd_fill(1.0);
+        assert_eq!(evenodd.inside[beam], vec![false, true, false, true, false]);
+        let nonzero = table.nonzero_fill(1.0);
+        assert_eq!(nonzero.inside[beam], vec![false, true, true, true, false]);
+        let nonzero = table.nonzero_all();
+        assert_eq!(nonzero.inside[beam], vec![false, true, true, true, false]);
+        let nonzero = table.nonzero_fill(2.0);
+        assert_eq!(nonzero.inside[beam], vec![false; 5]);
+
+        // vertical edges add no winding, zero width beams at them stay bounded
+        let vertical = table.actives[0][1];
+        assert_eq!(table.geometry.winding(vertical, table.snap), 0);
+        for mask in [table.nonzero_all(), table.nonzero_fill(1.0)] {
+            for inside in &mask.inside {
+                assert!(!inside[0] && !inside[inside.len() - 1]);
+            }
+        }
+    }
+
+    #[test]
+    fn pairwise_layer_ops() {
+        let mut segments = Geomstr::new();
+        segments.rect(0.0, 0.0, 100.0, 100.0, 1.0);
+        segments.rect(50.0, 50.0, 100.0, 100.0, 2.0);
+        let mut table = BeamTable::new(segments, None);
+        table.build().unwrap();
+        // beam between x=50 and x=100, actives are y=0, y=50, y=100, y=150.
+        let beam = table.events.iter().position(|e| e.x > 75.0).unwrap() - 1;
+        assert_eq!(table.actives[beam].len(), 4);
+        let mask = table.intersection(1.0, 2.0);
+        assert_eq!(mask.inside[beam], vec![false, false, true, false, false]);
+        let mask = table.difference(1.0, 2.0);
+        assert_eq!(mask.inside[beam], vec![false, true, false, false, false]);
+        let mask = table.difference(2.0, 1.0);
+        assert_eq!(mask.inside[beam], vec![false, false, false, true, false]);
+        let mask = table.xor(1.0, 2.0);
+        assert_eq!(mask.inside[beam], vec![false, true, false, true, false]);
+    }
+
+    #[test]
+    fn flatten_curves() {
+        let mut segments = Geomstr::new();
+        segments.arc((100.0, 0.0), (0.0, 100.0), (-100.0, 0.0), 1.0);
+        segments.arc((-100.0, 0.0), (0.0, -100.0), (100.0, 0.0), 1.0);
+        segments.quad((0.0, 0.0), (50.0, 100.0), (100.0, 0.0), 2.0);
+        segments.cubic((0.0, 0.0), (0.0, 100.0), (100.0, 100.0), (100.0, 0.0), 3.0);
+        assert!(segments.has_curves());
+        let flat = segments.flatten(0.01);
+        assert!(!flat.has_curves());
+        for line in &flat.segments {
+            if line.settings() == 1.0 {
+                assert!((f64::hypot(line.start().x, line.start().y) - 100.0).abs() < 1e-9);
+            }
+        }
+        let quad_mid = segments.point(2, 0.5);
+        assert_eq!(quad_mid, (50.0, 50.0).into());
+        let cubic_mid = segments.point(3, 0.5);
+        assert_eq!(cubic_mid, (50.0, 75.0).into());
+
+        let mut table = BeamTable::new(segments, None);
+        table.build().unwrap();
+        assert!(!table.geometry.has_curves());
+        let beam = table.events.iter().position(|e| e.x > 0.0).unwrap() - 1;
+        let mask = table.evenodd_fill(1.0);
+        let boundaries = mask.inside[beam].windows(2).filter(|w| w[0] != w[1]).count();
+        assert_eq!(boundaries, 2);
+    }
+
+    #[test]
+    fn segment_tuple_roundtrip() {
+        let tuples: Vec<SegmentTuple> = vec![
+            ((0.0, 0.0), (0.0, 0.0), (41.0, 3.0), (0.0, 0.0), (10.0, 5.0)),
+            ((0.0, 0.0), (1.0, 2.0), (121.0, 1.0), (3.0, 4.0), (5.0, 6.0)),
+            ((7.0, 8.0), (9.0, 1.5), (77.0, 2.0), (2.5, 3.5), (4.5, 5.5)),
+        ];
+        let geometry = Geomstr::from_tuples(tuples.clone());
+        assert_eq!(geometry.segments[0].kind(), SegmentKind::Line);
+        assert_eq!(geometry.segments[0].settings(), 3.0);
+        assert_eq!(geometry.segments[1].kind(), SegmentKind::Cubic);
+        assert_eq!(geometry.segments[1].controls().1, (3.0, 4.0).into());
+        assert_eq!(geometry.segments[2].kind(), SegmentKind::Other(77.0));
+        assert_eq!(geometry.to_tuples(), tuples);
+        assert_eq!(
+            Segment::from(tuples[1]).reversed().reversed(),
+            geometry.segments[1]
+        );
+    }
+
+    /// Signed areas of the rings in polygon output.
+    fn ring_areas(geometry: &Geomstr) -> Vec<f64> {
+        let mut areas = Vec::new();
+        let mut area = 0.0;
+        for segment in &geometry.segments {
+            if segment.kind() == SegmentKind::End {
+                areas.push(area);
+                area = 0.0;
+                continue;
+            }
+            let (a, b) = (segment.start(), segment.end());
+            area += (a.x * b.y - b.x * a.y) / 2.0;
+        }
+        areas
+    }
+
+    #[test]
+    fn polygons_union() {
+        let mut segments = Geomstr::new();
+        segments.rect(0.0, 0.0, 100.0, 100.0, 1.0);
+        segments.rect(50.0, 50.0, 100.0, 100.0, 2.0);
+        let mut table = BeamTable::new(segments, None);
+        table.build().unwrap();
+        let polygons = table.create_polygons(table.union_all()).unwrap();
+        let lines = polygons
+            .segments
+            .iter()
+            .filter(|s| s.kind() == SegmentKind::Line)
+            .count();
+        assert_eq!(lines, 8);
+        let areas = ring_areas(&polygons);
+        assert_eq!(areas.len(), 1);
+        assert!((areas[0] - 17500.0).abs() < 1e-6);
+        for pair in polygons.segments.windows(2) {
+            if pair[1].kind() == SegmentKind::Line {
+                assert_eq!(pair[0].end(), pair[1].start());
+            }
+        }
+    }
+
+    #[test]
+    fn polygons_hole() {
+        let mut segments = Geomstr::new();
+        segments.rect(0.0, 0.0, 100.0, 100.0, 1.0);
+        segments.rect(25.0, 25.0, 50.0, 50.0, 1.0);
+        let mut table = BeamTable::new(segments, None);
+        table.build().unwrap();
+        let polygons = table.create_polygons(table.evenodd_fill(1.0)).unwrap();
+        let mut areas = ring_areas(&polygons);
+        areas.sort_by(f64::total_cmp);
+        assert_eq!(areas.len(), 2);
+        assert!((areas[0] + 2500.0).abs() < 1e-6);
+        assert!((areas[1] - 10000.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn locate_points() {
+        let mut segments = Geomstr::new();
+        segments.rect(0.0, 0.0, 100.0, 100.0, 1.0);
+        segments.rect(25.0, 25.0, 50.0, 50.0, 1.0);
+        let mut table = BeamTable::new(segments, None);
+        table.build().unwrap();
+        assert_eq!(table.locate(-10.0, 50.0).unwrap(), None);
+        let (beam, gap) = table.locate(50.0, 50.0).unwrap().unwrap();
+        assert!(table.events[beam].x <= 50.0 && 50.0 < table.events[beam + 1].x);
+        assert_eq!(gap, 2);
+        assert_eq!(table.locate(50.0, -10.0).unwrap().unwrap().1, 0);
+        assert_eq!(table.locate(50.0, 110.0).unwrap().unwrap().1, 4);
+
+        let mask = table.evenodd_fill(1.0);
+        assert!(mask.contains(&table, 10.0, 10.0).unwrap());
+        assert!(mask.contains(&table, 80.0, 50.0).unwrap());
+        assert!(!mask.contains(&table, 50.0, 50.0).unwrap());
+        assert!(!mask.contains(&table, 150.0, 50.0).unwrap());
+        assert!(!mask.contains(&table, -5.0, 50.0).unwrap());
+        assert!(!mask.contains(&table, 50.0, 120.0).unwrap());
+    }
+
+    #[test]
+    fn hatch_square() {
+        let mut segments = Geomstr::new();
+        segments.rect(0.0, 0.0, 100.0, 100.0, 1.0);
+        segments.rect(25.0, 25.0, 50.0, 50.0, 1.0);
+        let mut table = BeamTable::new(segments, None);
+        table.build().unwrap();
+        let mask = table.evenodd_fill(1.0);
+
+        let hatch = table.hatch(&mask, 10.0, 0.0, false, 0.0).unwrap();
+        // 9 scanlines, the 5 crossing the hole are split in two.
+        assert_eq!(hatch.segments.len(), 14);
+        for line in &hatch.segments {
+            let (start, end) = (line.start(), line.end());
+            assert_eq!(start.x, end.x);
+            assert!(start.y < end.y);
+            assert!(mask.contains(&table, start.x, (start.y + end.y) / 2.0).unwrap());
+        }
+
+        let hatch = table.hatch(&mask, 10.0, 0.0, true, 5.0).unwrap();
+        assert_eq!(hatch.segments[0].start(), (10.0, 5.0).into());
+        assert_eq!(hatch.segments[0].end(), (10.0, 95.0).into());
+        assert_eq!(hatch.segments[1].start(), (20.0, 95.0).into());
+        assert_eq!(hatch.segments[1].end(), (20.0, 5.0).into());
+        assert_eq!(hatch.segments[2].start(), (30.0, 5.0).into());
+        assert_eq!(hatch.segments[2].end(), (30.0, 20.0).into());
+
+        let hatch = table.hatch(&mask, 10.0, std::f64::consts::FRAC_PI_2, false, 0.0).unwrap();
+        assert_eq!(hatch.segments.len(), 14);
+        for line in &hatch.segments {
+            let (start, end) = (line.start(), line.end());
+            assert!((start.y - end.y).abs() < 1e-9);
+            assert!(mask.contains(&table, (start.x + end.x) / 2.0, start.y).unwrap());
+        }
+
+        for spacing in [0.0, -10.0, f64::NAN, f64::INFINITY] {
+            let hatch = table.hatch(&mask, spacing, 0.0, false, 0.0);
+            assert!(matches!(hatch, Err(BeamTableError::InvalidSpacing(_))));
+        }
+        let hatch = table.hatch(&mask, 10.0, 0.0, false, f64::NAN);
+        assert!(matches!(hatch, Err(BeamTableError::NonFiniteInset(_))));
+
+        // filled gaps on both sides of an inner edge make a single line
+        let mut segments = Geomstr::new();
+        segments.rect(0.0, 0.0, 10.0, 10.0, 1.0);
+        segments.rect(5.0, 5.0, 10.0, 10.0, 2.0);
+        let mut table = BeamTable::new(segments, None);
+        table.build().unwrap();
+        let mask = table.union_all();
+        let hatch = table.hatch(&mask, 2.5, 0.0, false, 0.0).unwrap();
+        assert_eq!(hatch.segments.len(), 5);
+        assert_eq!(hatch.segments[1].start(), (5.0, 0.0).into());
+        assert_eq!(hatch.segments[1].end(), (5.0, 15.0).into());
+        let mask = table.nonzero_all();
+        let hatch = table.hatch(&mask, 2.5, 0.0, false, 0.0).unwrap();
+        assert_eq!(hatch.segments.len(), 5);
+    }
+
+    #[test]
+    fn sweep_status_matches_vec() {
+        let mut rng = ThreadRng::default();
+        let keys: Vec<u32> = (0..500).map(|_| rng.gen_range(0..1000)).collect();
+        let mut status = SweepStatus::new(keys.len());
+        let mut expected: Vec<usize> = Vec::new();
+        for _ in 0..5000 {
+            let segment = rng.gen_range(0..keys.len());
+            if status.contains(segment) {
+                let position = expected.iter().position(|&e| e == segment).unwrap();
+                let prev = position.checked_sub(1).map(|p| expected[p]);
+                let next = expected.get(position + 1).copied();
+                assert_eq!(status.prev(segment), prev);
+                assert_eq!(status.next(segment), next);
+                expected.remove(position);
+                status.remove(segment);
+            } else {
+                let position = expected.partition_point(|&e| keys[e] <= keys[segment]);
+                expected.insert(position, segment);
+                status.insert(segment, |other| keys[segment] < keys[other]);
+            }
+        }
+        let mut ordered = Vec::new();
+        status.write_ordered(&mut ordered);
+        assert_eq!(ordered, expected);
+    }
+
+    #[test]
+    fn actives_flat_storage() {
+        let mut actives = Actives::new();
+        assert!(actives.is_empty());
+        assert_eq!(actives.last(), None);
+        actives.push(&[3, 1]);
+        actives.push(&[]);
+        actives.push(&[4, 1, 5]);
+        assert_eq!(actives.len(), 3);
+        assert_eq!(&actives[0], &[3, 1]);
+        assert_eq!(actives.get(1), Some(&[][..]));
+        assert_eq!(actives.last(), Some(&[4, 1, 5][..]));
+        assert_eq!(actives.get(3), None);
+        let collected: Vec<&[usize]> = actives.iter().collect();
+        assert_eq!(collected, vec![&[3, 1][..], &[], &[4, 1, 5]]);
+        assert_eq!(format!("{:?}", actives), "[[3, 1], [], [4, 1, 5]]");
+    }
+
+    #[test]
+    fn orient2d_exact_near_collinear() {
+        // Perturb p by ulps near the line through q and r, check the sign against exact integers.
+        let ulp = f64::EPSILON / 2.0;
+        let scale = (1u64 << 54) as i128;
+        let q = Point::new(12.0, 12.0);
+        let r = Point::new(24.0, 24.0);
+        let (qx, qy, rx, ry) = (12 * scale, 12 * scale, 24 * scale, 24 * scale);
+        let mut naive_wrong = 0;
+        for i in 0..64 {
+            for j in 0..64 {
+                let p = Point::new(0.5 + i as f64 * ulp, 0.5 + j as f64 * ulp);
+                let px = scale / 2 + i as i128 * 2;
+                let py = scale / 2 + j as i128 * 2;
+                let exact = (px - rx) * (qy - ry) - (py - ry) * (qx - rx);
+                let orientation = orient2d(&p, &q, &r);
+                assert_eq!(orientation.partial_cmp(&0.0), exact.partial_cmp(&0));
+                let naive = (p.x - r.x) * (q.y - r.y) - (p.y - r.y) * (q.x - r.x);
+                if naive.partial_cmp(&0.0) != exact.partial_cmp(&0) {
+                    naive_wrong += 1;
+                }
+            }
+        }
+        assert!(naive_wrong > 0);
+    }
+
+    #[test]
+    fn snap_round_grid() {
+        let mut segments = Geomstr::new();
+        {
+            let mut rng = ThreadRng::default();
+            for c in 0..5 {
+                for _i in 0..40 {
+                    segments.line(
+                        (rng.gen_range(0.0..100.0), rng.gen_range(0.0..100.0)),
+                        (rng.gen_range(0.0..100.0), rng.gen_range(0.0..100.0)),
+                        c as f64,
+                    )
+                }
+            }
+        }
+        let mut table = BeamTable::new(segments, Some(1.0));
+        table.build().unwrap();
+        for line in &table.geometry.segments {
+            for pt in [line.start(), line.end()] {
+                assert_eq!(pt.x, pt.x.round());
+                assert_eq!(pt.y, pt.y.round());
+            }
+        }
+        for event in &table.events {
+            assert_eq!(event.x, event.x.round());
+            assert_eq!(event.y, event.y.round());
+        }
+        // Snap rounded lines only meet at their endpoints.
+        assert!(table.intersections.is_empty());
+        for beam in 0..table.actives.len() - 1 {
+            if table.events[beam].x == table.events[beam + 1].x {
+                continue;
+            }
+            let x = (table.events[beam].x + table.events[beam + 1].x) / 2.0;
+            let actives = &table.actives[beam];
+            for i in 1..actives.len() {
+                let last_pos = table.geometry.y_intercept(actives[i - 1], x, 0.0);
+                let pos = table.geometry.y_intercept(actives[i], x, 0.0);
+                assert!(last_pos <= pos);
+            }
+        }
+
+        let mut square = Geomstr::new();
+        square.rect(0.0, 0.0, 10.0, 10.0, 1.0);
+        for grid in [0.0, -1.0, f64::NAN, f64::INFINITY, 1e-300] {
+            let mut table = BeamTable::new(square.clone(), Some(grid));
+            assert!(matches!(table.build(), Err(BeamTableError::InvalidGrid(_))));
+            assert!(!table.is_built());
+        }
+        let mut empty = BeamTable::new(Geomstr::new(), Some(1.0));
+        empty.build().unwrap();
+        assert!(empty.events.is_empty());
+    }
+
+    #[test]
+    fn snap_tolerance() {
+        let mut segments = Geomstr::new();
+        segments.rect(0.0, 0.0, 10.0, 10.0, 1.0);
+        let mut table = BeamTable::new(segments.clone(), None);
+        table.build().unwrap();
+        assert!(table.union_all().contains(&table, 9.7, 5.0).unwrap());
+        let mut table = BeamTable::new(segments, None);
+        table.snap = 0.5;
+        table.build().unwrap();
+        assert!(!table.union_all().contains(&table, 9.7, 5.0).unwrap());
+        assert_eq!(table.actives_at(9.7, 5.0), table.actives_at(10.0, 5.0));
+
+        // The apex is 1e-4 apart, stitched into one ring within a snap of 1e-3.
+        let mut segments = Geomstr::new();
+        segments.line((0.0, 0.0), (10.0, 0.0), 1.0);
+        segments.line((10.0, 0.0), (5.0, 10.0), 1.0);
+        segments.line((5.0, 10.0001), (0.0, 0.0), 1.0);
+        let mut table = BeamTable::new(segments, None);
+        table.snap = 1e-3;
+        table.build().unwrap();
+        let polygons = table.create_polygons(table.nonzero_all()).unwrap();
+        let lines = &polygons.segments[..polygons.segments.len() - 1];
+        assert_eq!(lines.len(), 3);
+        assert!(lines.iter().all(|s| s.kind() == SegmentKind::Line));
+        assert_eq!(polygons.segments[3].kind(), SegmentKind::End);
+        assert!(lines[2].end().eq_within(&lines[0].start(), 1e-3));
+        assert!((ring_areas(&polygons)[0] - 50.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn overlapping_shared_edge() {
+        // Tiled squares sharing the edge y=10, on one layer and on two layers.
+        for (lower, upper) in [(1.0, 1.0), (1.0, 2.0)] {
+            let mut segments = Geomstr::new();
+            segments.rect(0.0, 0.0, 10.0, 10.0, lower);
+            segments.rect(0.0, 10.0, 10.0, 10.0, upper);
+            let mut table = BeamTable::new(segments, None);
+            table.build().unwrap();
+            assert_eq!(table.overlaps, vec![(2, 4)]);
+            let mask = if lower == upper {
+                table.evenodd_fill(1.0)
+            } else {
+                table.union_all()
+            };
+            assert!(mask.contains(&table, 5.0, 9.0).unwrap());
+            assert!(mask.contains(&table, 5.0, 11.0).unwrap());
+            let geometry = table.create(mask.clone(), false).unwrap();
+            for line in &geometry.segments {
+                let (start, end) = (line.start(), line.end());
+                assert!(!(start.y == 10.0 && end.y == 10.0 && start.x != end.x));
+            }
+            let areas = ring_areas(&table.create_polygons(mask).unwrap());
+            assert_eq!(areas.len(), 1);
+            assert!((areas[0] - 200.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn vertical_edges() {
+        // Squares side by side sharing x=10 fully, and offset so they share part of it.
+        for offset in [0.0, 5.0] {
+            let mut segments = Geomstr::new();
+            segments.rect(0.0, 0.0, 10.0, 10.0, 1.0);
+            segments.rect(10.0, offset, 10.0, 10.0, 2.0);
+            let mut table = BeamTable::new(segments, None);
+            table.build().unwrap();
+            let mask = table.union_all();
+
+            // Points on a vertical edge belong to the space to its right.
+            assert!(mask.contains(&table, 0.0, 5.0).unwrap());
+            assert!(mask.contains(&table, 10.0, 7.0).unwrap());
+            assert!(!mask.contains(&table, 20.0, 7.0).unwrap());
+
+            let geometry = table.create(mask.clone(), false).unwrap();
+            let mut vertical = 0.0;
+            for line in &geometry.segments {
+                let (start, end) = (line.start(), line.end());
+                assert!(start != end);
+                if start.x == end.x {
+                    // Filled space is on the left of each line.
+                    let y = (start.y + end.y) / 2.0;
+                    assert_eq!(mask.contains(&table, start.x - 0.5, y).unwrap(), end.y > start.y);
+                    if start.x == 10.0 {
+                        vertical += (end.y - start.y).abs();
+                    }
+                }
+            }
+            assert_eq!(vertical, 2.0 * offset);
+
+            let areas = ring_areas(&table.create_polygons(mask).unwrap());
+            assert_eq!(areas.len(), 1);
+            assert!((areas[0] - 200.0).abs() < 1e-9);
+        }
+
+        // The flattened circle has a vertex within EPSILON of x=10, sorting onto its scanline.
+        let mut segments = Geomstr::new();
+        segments.rect(0.0, 0.0, 10.0, 10.0, 1.0);
+        segments.arc((15.0, 10.0), (10.0, 15.0), (5.0, 10.0), 2.0);
+        segments.arc((5.0, 10.0), (10.0, 5.0), (15.0, 10.0), 2.0);
+        let mut table = BeamTable::new(segments, None);
+        table.build().unwrap();
+        let geometry = table.create(table.intersection(1.0, 2.0), false).unwrap();
+        let vertical: f64 = geometry
+            .segments
+            .iter()
+            .filter(|line| line.start().x == 10.0 && line.end().x == 10.0)
+            .map(|line| (line.end().y - line.start().y).abs())
+            .sum();
+        assert!((vertical - 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn near_vertical_edges() {
+        // Rotating leaves the sides a rounding error away from vertical.
+        let mut rotated = Geomstr::new();
+        rotated.rect(0.0, 0.0, 10.0, 20.0, 1.0);
+        rotated.transform(&Matrix::rotate(std::f64::consts::FRAC_PI_2));
+        let mut skewed = Geomstr::new();
+        skewed.line((0.0, 0.0), (10.0, 0.0), 1.0);
+        skewed.line((10.0, 0.0), (10.0 + 1e-10, 20.0), 1.0);
+        skewed.line((10.0 + 1e-10, 20.0), (0.0, 20.0), 1.0);
+        skewed.line((0.0, 20.0), (0.0, 0.0), 1.0);
+        for segments in [rotated, skewed] {
+            let mut table = BeamTable::new(segments, None);
+            table.build().unwrap();
+            let boundary = table.create(table.union_all(), false).unwrap();
+            assert_eq!(boundary.segments.len(), 4);
+            assert!((boundary.length() - 60.0).abs() < 1e-6);
+            let polygons = table.create_polygons(table.union_all()).unwrap();
+            assert_eq!(ring_areas(&polygons).len(), 1);
+            assert!((polygons.area() - 200.0).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn errors_instead_of_panics() {
+        let mut segments = Geomstr::new();
+        segments.line((0.0, 0.0), (10.0, 0.0), 1.0);
+        segments.line((10.0, 0.0), (f64::NAN, 10.0), 1.0);
+        let mut table = BeamTable::new(segments, None);
+        let mask = table.union_all();
+        assert_eq!(table.create(mask, false).unwrap_err(), BeamTableError::NotBuilt);
+        assert_eq!(table.build(), Err(BeamTableError::NonFiniteSegment(1)));
+        assert_eq!(table.actives_at(0.0, 0.0), Err(BeamTableError::NotBuilt));
+
+        let mut empty = BeamTable::new(Geomstr::new(), None);
+        empty.build().unwrap();
+        assert_eq!(empty.actives_at(0.0, 0.0), Err(BeamTableError::Empty));
+        assert_eq!(empty.locate(0.0, 0.0), Ok(None));
+        assert!(empty.create(empty.union_all(), true).unwrap().segments.is_empty());
+
+        let mut segments = Geomstr::new();
+        segments.rect(0.0, 0.0, 10.0, 10.0, 1.0);
+        let mut table = BeamTable::new(segments, None);
+        table.build().unwrap();
+        assert_eq!(table.locate(f64::NAN, 0.0), Err(BeamTableError::NonFinitePoint));
+        let foreign = empty.union_all();
+        let created = table.create(foreign.clone(), false);
+        assert_eq!(created.unwrap_err(), BeamTableError::MaskMismatch);
+        assert_eq!(foreign.contains(&table, 5.0, 5.0), Err(BeamTableError::MaskMismatch));
+
+        let mut curve = Geomstr::new();
+        curve.quad((0.0, 0.0), (5.0, 10.0), (10.0, 0.0), 1.0);
+        let mut table = BeamTable::new(curve.clone(), None);
+        table.tolerance = 0.0;
+        assert_eq!(table.build(), Err(BeamTableError::InvalidTolerance(0.0)));
+        table.tolerance = f64::NAN;
+        assert!(matches!(table.build(), Err(BeamTableError::InvalidTolerance(_))));
+        assert!(table.geometry.has_curves());
+        assert_eq!(curve.flatten(0.0).segments.len(), MAX_FLATTEN_STEPS);
+        assert_eq!(curve.flatten(-1.0).segments.len(), MAX_FLATTEN_STEPS);
+
+        let mut rects = Geomstr::new();
+        rects.rect(0.0, 0.0, 10.0, 10.0, 1.0);
+        rects.rect(5.0, 5.0, 10.0, 10.0, 2.0);
+        for snap in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let mut table = BeamTable::new(rects.clone(), None);
+            table.snap = snap;
+            assert!(matches!(table.build(), Err(BeamTableError::InvalidSnap(_))));
+            assert!(!table.is_built());
+        }
+    }
+
+    #[test]
+    fn matrix_transform() {
+        let near = |a: (f64, f64), b: (f64, f64)| f64::hypot(a.0 - b.0, a.1 - b.1) < 1e-9;
+        let rotate = Matrix::rotate(std::f64::consts::FRAC_PI_2);
+        let matrix = rotate.then(&Matrix::translate(5.0, 0.0));
+        assert!(near(matrix.apply((1.0, 0.0)), (5.0, 1.0)));
+        let inverse = matrix.inverse().unwrap();
+        assert!(near(inverse.apply(matrix.apply((3.0, -2.0))), (3.0, -2.0)));
+        assert!(matrix.is_similarity());
+        assert!(!Matrix::skew(0.3, 0.0).is_similarity());
+        assert!(Matrix::scale(0.0, 1.0).inverse().is_none());
+
+        let mut geometry = Geomstr::new();
+        geometry.line((0.0, 0.0), (10.0, 0.0), 1.0);
+        geometry.quad((10.0, 0.0), (15.0, 5.0), (10.0, 10.0), 1.0);
+        geometry.cubic((10.0, 10.0), (8.0, 12.0), (2.0, 12.0), (0.0, 10.0), 1.0);
+        geometry.arc((0.0, 10.0), (-5.0, 5.0), (0.0, 0.0), 1.0);
+        geometry.end();
+
+        // Every segment type follows its points, arcs stay arcs.
+        let mut moved = geometry.clone();
+        moved.transform(&matrix);
+        assert_eq!(moved.segments.len(), geometry.segments.len());
+        assert_eq!(moved.segments[3].kind(), SegmentKind::Arc);
+        assert_eq!(moved.segments[4], Segment::end_path());
+        for i in 0..4 {
+            for t in 0..=8 {
+                let t = t as f64 / 8.0;
+                let p = geometry.point(i, t);
+                let q = moved.point(i, t);
+                assert!(near(matrix.apply((p.x, p.y)), (q.x, q.y)));
+            }
+        }
+
+        // Stretched, the arc becomes cubics along the ellipse.
+        let mut stretched = geometry.clone();
+        stretched.transform(&Matrix::scale(2.0, 1.0));
+        let cubics = &stretched.segments[3..stretched.segments.len() - 1];
+        assert_eq!(cubics.len(), 4);
+        assert!(cubics.iter().all(|s| s.kind() == SegmentKind::Cubic));
+        assert_eq!(cubics[0].start(), Point::new(0.0, 10.0));
+        assert_eq!(cubics[3].end(), Point::new(0.0, 0.0));
+        for i in 3..7 {
+            for t in 0..=8 {
+                let p = stretched.point(i, t as f64 / 8.0);
+                let r = f64::hypot(p.x / 2.0, p.y - 5.0);
+                assert!((r - 5.0).abs() < 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn measurements() {
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+        let mut square = Geomstr::new();
+        square.line((0.0, 0.0), (10.0, 0.0), 1.0);
+        square.line((10.0, 0.0), (10.0, 10.0), 1.0);
+        square.line((10.0, 10.0), (0.0, 10.0), 1.0);
+        square.line((0.0, 10.0), (0.0, 0.0), 1.0);
+        assert!(close(square.length(), 40.0));
+        assert!(close(square.area(), 100.0));
+        square.reverse(0);
+        assert!(close(square.area(), 0.0));
+        assert_eq!(Geomstr::new().bbox(), None);
+
+        // Circle of radius 5 from two arcs, counter-clockwise.
+        let mut circle = Geomstr::new();
+        circle.arc((5.0, 0.0), (0.0, 5.0), (-5.0, 0.0), 2.0);
+        circle.arc((-5.0, 0.0), (0.0, -5.0), (5.0, 0.0), 2.0);
+        circle.end();
+        let pi = std::f64::consts::PI;
+        assert!(close(circle.area(), 25.0 * pi));
+        assert!(close(circle.length(), 10.0 * pi));
+        let (x0, y0, x1, y1) = circle.bbox().unwrap();
+        assert!(close(x0, -5.0) && close(y0, -5.0) && close(x1, 5.0) && close(y1, 5.0));
+
+        // Beziers closed by a line are clockwise, their areas are exact.
+        let mut curves = Geomstr::new();
+        curves.quad((0.0, 0.0), (5.0, 10.0), (10.0, 0.0), 3.0);
+        curves.line((10.0, 0.0), (0.0, 0.0), 3.0);
+        curves.end();
+        curves.cubic((0.0, 0.0), (0.0, 10.0), (10.0, 10.0), (10.0, 0.0), 4.0);
+        curves.line((10.0, 0.0), (0.0, 0.0), 4.0);
+        curves.end();
+        assert!(close(curves.area(), -100.0 / 3.0 - 60.0));
+        assert!((curves.area() - curves.flatten(1e-4).area()).abs() < 1e-2);
+        assert!((curves.length() - curves.flatten(1e-4).length()).abs() < 1e-2);
+        assert_eq!(curves.bbox_for_layer(3.0), Some((0.0, 0.0, 10.0, 5.0)));
+        assert_eq!(curves.bbox_for_layer(4.0), Some((0.0, 0.0, 10.0, 7.5)));
+        assert_eq!(curves.bbox_for_layer(5.0), None);
+
+        // Points are drawn with no length, unknown codes are skipped like separators.
+        let point = |x: f64, y: f64, kind: SegmentKind, settings: f64| {
+            Segment::from(((x, y), (x, y), (kind.code(), settings), (x, y), (x, y)))
+        };
+        let marks = [
+            point(-5.0, 20.0, SegmentKind::Point, 6.0),
+            point(50.0, 50.0, SegmentKind::Other(99.0), 6.0),
+            point(8.0, 1.0, SegmentKind::Point, 7.0),
+        ];
+        let mut mixed = Geomstr::new();
+        mixed.segments.push(marks[0]);
+        mixed.line((0.0, 0.0), (3.0, 4.0), 6.0);
+        mixed.segments.extend(&marks[1..]);
+        mixed.line((3.0, 4.0), (0.0, 0.0), 7.0);
+        assert!(close(mixed.length(), 10.0));
+        assert!(close(mixed.area(), 0.0));
+        assert_eq!(mixed.bbox(), Some((-5.0, 0.0, 8.0, 20.0)));
+        assert_eq!(mixed.bbox_for_layer(6.0), Some((-5.0, 0.0, 3.0, 20.0)));
+        assert_eq!(mixed.bbox_for_layer(7.0), Some((0.0, 0.0, 8.0, 4.0)));
+
+        let mut segments = Geomstr::new();
+        segments.rect(0.0, 0.0, 10.0, 10.0, 1.0);
+        segments.rect(5.0, 5.0, 10.0, 10.0, 2.0);
+        assert_eq!(segments.bbox(), Some((0.0, 0.0, 15.0, 15.0)));
+        assert_eq!(segments.bbox_for_layer(2.0), Some((5.0, 5.0, 15.0, 15.0)));
+        let mut table = BeamTable::new(segments, None);
+        assert!(matches!(
+            table.area(&BoolOp::new(vec![])),
+            Err(BeamTableError::NotBuilt)
+        ));
+        table.build().unwrap();
+        let area = |mask: BoolOp| table.area(&mask).unwrap();
+        assert!(close(area(table.union_all()), 175.0));
+        assert!(close(area(table.intersection(1.0, 2.0)), 25.0));
+        assert!(close(area(table.xor(1.0, 2.0)), 150.0));
+        assert!(close(area(table.difference(1.0, 2.0)), 75.0));
+        assert_eq!(area(!table.union_all()), f64::INFINITY);
+        let polygons = table.create_polygons(table.union_all()).unwrap();
+        assert!(close(polygons.area(), 175.0));
+        assert!(close(area(table.nonzero_all()), 175.0));
+        assert!(close(area(table.nonzero_fill(1.0)), 100.0));
+        assert!(close(area(table.nonzero_fill(2.0)), 100.0));
+        assert_eq!(area(!table.nonzero_all()), f64::INFINITY);
+        let polygons = table.create_polygons(table.nonzero_all()).unwrap();
+        assert!(close(polygons.area(), 175.0));
+    }
+
+    #[cfg(feature = "svg")]
+    #[test]
+    fn svg_import() {
+        use crate::svg::{svg_layer, svg_paths, svg_settings, FillRule, SvgError, SvgPath};
+
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg">
+            <g id="layer2" style="fill-rule:evenodd">
+                <path d="M0 0 H10 V10 H0 Z m2 2 h6 v6 h-6 z"/>
+                <circle cx="20" cy="5" r="5" fill-rule="nonzero"/>
+            </g>
+            <g transform="scale(2 1)"><ellipse cx="20" cy="5" rx="3" ry="3"/></g>
+            <rect x="0" y="20" width="4" height="4" display="none"/>
+        </svg>"#;
+        let paths = svg_paths(svg, 0.01).unwrap();
+        assert_eq!(paths.len(), 3);
+        assert_eq!((paths[0].layer, paths[0].fill_rule), (2, FillRule::EvenOdd));
+        assert_eq!((paths[1].layer, paths[1].fill_rule), (2, FillRule::NonZero));
+        assert_eq!((paths[2].layer, paths[2].fill_rule), (0, FillRule::NonZero));
+        let settings = svg_settings(2, FillRule::EvenOdd);
+        assert_eq!(svg_layer(settings), (2, FillRule::EvenOdd));
+
+        // two closed subpaths, each followed by a separator
+        let segments = &paths[0].geometry.segments;
+        let kinds: Vec<SegmentKind> = segments.iter().map(|s| s.kind()).collect();
+        assert_eq!(kinds.iter().filter(|&&k| k == SegmentKind::End).count(), 2);
+        assert_eq!(kinds.iter().filter(|&&k| k == SegmentKind::Line).count(), 8);
+
+        // circles keep native arcs, a circle stretched into an ellipse becomes cubics
+        let circle = &paths[1].geometry.segments;
+        assert!(circle[..2].iter().all(|s| s.kind() == SegmentKind::Arc));
+        assert_eq!(circle[1].end(), Point::new(25.0, 5.0));
+        let ellipse = &paths[2].geometry.segments;
+        let (curves, ends): (Vec<&Segment>, Vec<&Segment>) = ellipse
+            .iter()
+            .partition(|s| s.kind() == SegmentKind::Cubic);
+        assert!(curves.len() >= 4);
+        assert_eq!(ends.len(), 1);
+        assert_eq!(ellipse[0].start(), Point::new(46.0, 5.0));
+        for t in 0..=10 {
+            let p = paths[2].geometry.point(0, t as f64 / 10.0);
+            let r = f64::hypot((p.x - 40.0) / 2.0, p.y - 5.0);
+            assert!((r - 3.0).abs() < 0.01);
+        }
+
+        // the even-odd square has a hole
+        let geometry = Geomstr::from_svg(svg, 0.01).unwrap();
+        let mut table = BeamTable::new(geometry, None);
+        table.build().unwrap();
+        let mask = table.evenodd_fill(svg_settings(2, FillRule::EvenOdd));
+        assert!(mask.contains(&table, 1.0, 1.0).unwrap());
+        assert!(!mask.contains(&table, 5.0, 5.0).unwrap());
+        let circle = table.nonzero_fill(svg_settings(2, FillRule::NonZero));
+        assert!(circle.contains(&table, 20.0, 5.0).unwrap());
+
+        // the root viewBox in millimeters and lengths with units come out in pixels
+        let mm = 96.0 / 25.4;
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink"
+            width="100mm" height="50mm" viewBox="0 0 100 50">
+            <defs><symbol id="unit"><rect width="1" height="1"/></symbol></defs>
+            <rect x="10" y="10" width="20" height="10"/>
+            <line x1="0" y1="0" x2="1in" y2="0"/>
+            <use xlink:href="#unit" x="40" y="20" transform="scale(2)"/>
+            <use href="#missing"/>
+            <svg x="50" y="0" width="20" height="10" viewBox="0 0 10 10">
+                <rect width="10" height="10"/>
+            </svg>
+        </svg>"##;
+        let paths = svg_paths(svg, 0.01).unwrap();
+        assert_eq!(paths.len(), 4);
+        let near = |a: (f64, f64, f64, f64), b: (f64, f64, f64, f64)| {
+            [(a.0, b.0), (a.1, b.1), (a.2, b.2), (a.3, b.3)]
+                .iter()
+                .all(|(a, b)| (a - b).abs() < 1e-9)
+        };
+        let bbox = |path: &SvgPath| path.geometry.bbox().unwrap();
+        assert!(near(
+            bbox(&paths[0]),
+            (10.0 * mm, 10.0 * mm, 30.0 * mm, 20.0 * mm)
+        ));
+        assert!(near(bbox(&paths[1]), (0.0, 0.0, 96.0 * mm, 0.0)));
+        assert!(near(
+            bbox(&paths[2]),
+            (80.0 * mm, 40.0 * mm, 82.0 * mm, 42.0 * mm)
+        ));
+        // a nested viewBox is fitted centered, keeping its aspect ratio
+        assert!(near(
+            bbox(&paths[3]),
+            (55.0 * mm, 0.0, 65.0 * mm, 10.0 * mm)
+        ));
+
+        // a use that refers to itself stops instead of recursing forever
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg">
+            <g id="loop"><rect width="1" height="1"/><use href="#loop" x="1"/></g>
+        </svg>"##;
+        assert!(svg_paths(svg, 0.01).unwrap().len() > 1);
+
+        // references multiplying at every level fail instead of expanding without bound
+        let mut svg = String::from(r#"<svg xmlns="http://www.w3.org/2000/svg"><defs>"#);
+        svg.push_str(r#"<rect id="l0" width="1" height="1"/>"#);
+        for level in 1..=8 {
+            svg.push_str(&format!(r#"<g id="l{}">"#, level));
+            for _ in 0..10 {
+                svg.push_str(&format!(r##"<use href="#l{}"/>"##, level - 1));
+            }
+            svg.push_str("</g>");
+        }
+        svg.push_str(r##"</defs><use href="#l8"/></svg>"##);
+        assert!(matches!(svg_paths(&svg, 0.01), Err(SvgError::TooManyUses)));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_roundtrip() {
+        let mut segments = Geomstr::new();
+        segments.rect(0.0, 0.0, 10.0, 10.0, 1.0);
+        segments.rect(5.0, 5.0, 10.0, 10.0, 2.0);
+        segments.arc((20.0, 0.0), (25.0, 5.0), (30.0, 0.0), 3.0);
+        let mut table = BeamTable::new(segments, None);
+        let unbuilt = BeamTable::from_json(&table.to_json().unwrap()).unwrap();
+        assert!(!unbuilt.is_built());
+        assert_eq!(unbuilt.geometry.to_tuples(), table.geometry.to_tuples());
+        table.build().unwrap();
+
+        let json = table.to_json().unwrap();
+        let bytes = table.to_bytes().unwrap();
+        assert!(bytes.len() < json.len());
+        for loaded in [
+            BeamTable::from_json(&json).unwrap(),
+            BeamTable::from_bytes(&bytes).unwrap(),
+        ] {
+            assert!(loaded.is_built());
+            assert_eq!(loaded.geometry.to_tuples(), table.geometry.to_tuples());
+            assert_eq!(loaded.events, table.events);
+            assert!(loaded.actives.iter().eq(table.actives.iter()));
+            assert_eq!(loaded.intersections, table.intersections);
+            assert_eq!(loaded.overlaps, table.overlaps);
+            let mask = loaded.intersection(1.0, 2.0);
+            assert!(mask.contains(&loaded, 7.0, 7.0).unwrap());
+            assert!(!mask.contains(&loaded, 2.0, 2.0).unwrap());
+        }
+
+        let mask = table.union_all();
+        let loaded: BoolOp = serde_json::from_str(&serde_json::to_string(&mask).unwrap()).unwrap();
+        assert_eq!(loaded.inside, mask.inside);
+
+        // Corrupt tables are rejected on load rather than panicking on use.
+        let offsets = json.replace("\"offsets\":[0,", "\"offsets\":[1,");
+        assert!(BeamTable::from_json(&offsets).is_err());
+        let mut short = table.clone();
+        short.events.pop();
+        assert!(BeamTable::from_bytes(&short.to_bytes().unwrap()).is_err());
+    }
 }
@@ -0,0 +1,163 @@
+//! Geomstr segments as N x 5 complex128 arrays, the layout of meerk40t's geomstr: start, c0,
+//! info (type + settings j), c1 and end of each segment. Arrays are read and written through the
+//! buffer protocol without converting elements to Python objects.
+
+use ::beamtable::{Geomstr, Segment, SegmentTuple};
+use pyo3::exceptions::{PyBufferError, PyValueError};
+use pyo3::ffi;
+use pyo3::prelude::*;
+use std::ffi::CStr;
+use std::os::raw::{c_int, c_void};
+use std::ptr;
+
+/// Floats per segment, five complex values.
+const FLOATS: usize = 10;
+
+/// Whether the object exports the buffer protocol, as numpy arrays do.
+pub(crate) fn is_buffer(obj: &PyAny) -> bool {
+    unsafe { ffi::PyObject_CheckBuffer(obj.as_ptr()) == 1 }
+}
+
+/// Read the segments of an N x 5 complex128 array, or any C-contiguous buffer of that layout.
+pub(crate) fn geomstr_from_buffer(obj: &PyAny) -> PyResult<Geomstr> {
+    let mut view = ffi::Py_buffer::new();
+    let flags = ffi::PyBUF_C_CONTIGUOUS | ffi::PyBUF_FORMAT;
+    if unsafe { ffi::PyObject_GetBuffer(obj.as_ptr(), &mut view, flags) } == -1 {
+        return Err(PyErr::fetch(obj.py()));
+    }
+    let view = BufferGuard(view);
+    let view = &view.0;
+
+    let format = if view.format.is_null() {
+        "B"
+    } else {
+        unsafe { CStr::from_ptr(view.format) }
+            .to_str()
+            .unwrap_or("")
+    };
+    let shape: &[isize] = if view.shape.is_null() {
+        &[]
+    } else {
+        unsafe { std::slice::from_raw_parts(view.shape, view.ndim as usize) }
+    };
+    if !is_complex128(format) || view.itemsize != 16 || shape.len() != 2 || shape[1] != 5 {
+        return Err(PyValueError::new_err(format!(
+            "expected an N x 5 complex128 array, got format {:?} with shape {:?}",
+            format, shape
+        )));
+    }
+    if !(view.buf as *const f64).is_aligned() {
+        return Err(PyValueError::new_err("array data is not aligned"));
+    }
+
+    let count = shape[0] as usize;
+    let floats = unsafe { std::slice::from_raw_parts(view.buf as *const f64, count * FLOATS) };
+    let mut geometry = Geomstr::new();
+    geometry.segments.reserve(count);
+    for f in floats.chunks_exact(FLOATS) {
+        let tuple: SegmentTuple = (
+            (f[0], f[1]),
+            (f[2], f[3]),
+            (f[4], f[5]),
+            (f[6], f[7]),
+            (f[8], f[9]),
+        );
+        geometry.segments.push(Segment::from(tuple));
+    }
+    Ok(geometry)
+}
+
+/// The segments as a numpy N x 5 complex128 array. The array views memory owned by a
+/// SegmentArray rather than copying it.
+pub(crate) fn geomstr_to_numpy(py: Python<'_>, geometry: &Geomstr) -> PyResult<PyObject> {
+    let array = Py::new(py, SegmentArray::new(geometry))?;
+    let numpy = py.import("numpy")?;
+    Ok(numpy.call_method1("asarray", (array,))?.into())
+}
+
+/// Internal: whether the buffer format string is native complex128.
+fn is_complex128(format: &str) -> bool {
+    let format = format.trim_start_matches(['@', '=']);
+    let format = if cfg!(target_endian = "little") {
+        format.trim_start_matches('<')
+    } else {
+        format.trim_start_matches(['>', '!'])
+    };
+    format == "Zd"
+}
+
+/// Internal: releases a buffer view when dropped.
+struct BufferGuard(ffi::Py_buffer);
+
+impl Drop for BufferGuard {
+    fn drop(&mut self) {
+        unsafe { ffi::PyBuffer_Release(&mut self.0) }
+    }
+}
+
+/// Segments stored as complex128 pairs of floats, exported through the buffer protocol as an
+/// N x 5 array.
+#[pyclass]
+pub(crate) struct SegmentArray {
+    data: Vec<f64>,
+    shape: [isize; 2],
+    strides: [isize; 2],
+}
+
+impl SegmentArray {
+    fn new(geometry: &Geomstr) -> SegmentArray {
+        let mut data = Vec::with_capacity(geometry.segments.len() * FLOATS);
+        for &segment in &geometry.segments {
+            let (start, c0, info, c1, end): SegmentTuple = segment.into();
+            data.extend_from_slice(&[
+                start.0, start.1, c0.0, c0.1, info.0, info.1, c1.0, c1.1, end.0, end.1,
+            ]);
+        }
+        SegmentArray {
+            data,
+            shape: [geometry.segments.len() as isize, 5],
+            strides: [(FLOATS * 8) as isize, 16],
+        }
+    }
+}
+
+#[pymethods]
+impl SegmentArray {
+    unsafe fn __getbuffer__(
+        mut slf: PyRefMut<'_, Self>,
+        view: *mut ffi::Py_buffer,
+        flags: c_int,
+    ) -> PyResult<()> {
+        if view.is_null() {
+            return Err(PyBufferError::new_err("View is null"));
+        }
+        (*view).obj = ffi::_Py_NewRef(slf.as_ptr());
+        (*view).buf = slf.data.as_mut_ptr() as *mut c_void;
+        (*view).len = (slf.data.len() * 8) as isize;
+        (*view).readonly = 0;
+        (*view).itemsize = 16;
+        (*view).format = if flags & ffi::PyBUF_FORMAT == ffi::PyBUF_FORMAT {
+            c"Zd".as_ptr() as *mut _
+        } else {
+            ptr::null_mut()
+        };
+        (*view).ndim = 2;
+        (*view).shape = if flags & ffi::PyBUF_ND == ffi::PyBUF_ND {
+            slf.shape.as_mut_ptr()
+        } else {
+            ptr::null_mut()
+        };
+        (*view).strides = if flags & ffi::PyBUF_STRIDES == ffi::PyBUF_STRIDES {
+            slf.strides.as_mut_ptr()
+        } else {
+            ptr::null_mut()
+        };
+        (*view).suboffsets = ptr::null_mut();
+        (*view).internal = ptr::null_mut();
+        Ok(())
+    }
+
+    fn __len__(&self) -> usize {
+        self.shape[0] as usize
+    }
+}
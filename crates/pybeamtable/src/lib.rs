@@ -1,52 +1,397 @@
-use ::beamtable::{BeamTable, Geomstr};
+// pyo3 0.20 expands #[pymethods] into impls nested in functions.
+#![allow(non_local_definitions)]
+
+mod array;
+
+use crate::array::{geomstr_from_buffer, geomstr_to_numpy, is_buffer};
+use ::beamtable::{BeamTable, BeamTableError, BoolOp, Geomstr, SegmentTuple};
+use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use std::sync::Mutex;
+use std::thread;
+
+/// Geometry as a list of geomstr segments.
+#[pyclass(name = "Geomstr", module = "pybeamtable")]
+#[derive(Clone)]
+struct PyGeomstr {
+    inner: Geomstr,
+}
+
+#[pymethods]
+impl PyGeomstr {
+    #[new]
+    #[pyo3(signature = (segments=None))]
+    fn new(segments: Option<Vec<SegmentTuple>>) -> Self {
+        let inner = match segments {
+            Some(segments) => Geomstr::from_tuples(segments),
+            None => Geomstr::new(),
+        };
+        PyGeomstr { inner }
+    }
+
+    /// Geometry from an N x 5 complex128 array in meerk40t's geomstr layout.
+    #[staticmethod]
+    fn from_array(array: &PyAny) -> PyResult<Self> {
+        Ok(PyGeomstr {
+            inner: geomstr_from_buffer(array)?,
+        })
+    }
+
+    /// The segments as an N x 5 complex128 numpy array.
+    fn as_array(&self, py: Python<'_>) -> PyResult<PyObject> {
+        geomstr_to_numpy(py, &self.inner)
+    }
+
+    /// Add a line from p0 to p1.
+    #[pyo3(signature = (p0, p1, settings=0.0))]
+    fn line(&mut self, p0: (f64, f64), p1: (f64, f64), settings: f64) {
+        self.inner.line(p0, p1, settings);
+    }
+
+    /// Add a rectangle.
+    #[pyo3(signature = (x, y, width, height, settings=0.0))]
+    fn rect(&mut self, x: f64, y: f64, width: f64, height: f64, settings: f64) {
+        self.inner.rect(x, y, width, height, settings);
+    }
+
+    /// The segments as geomstr tuples.
+    #[getter]
+    fn segments(&self) -> Vec<SegmentTuple> {
+        self.inner.to_tuples()
+    }
+
+    fn __len__(&self) -> usize {
+        self.inner.segments.len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Geomstr({} segments)", self.inner.segments.len())
+    }
+
+    /// Pickled as the segment tuples.
+    fn __getnewargs__(&self) -> (Vec<SegmentTuple>,) {
+        (self.inner.to_tuples(),)
+    }
+}
+
+/// Fill mask over the beams of a table.
+#[pyclass(name = "BoolOp", module = "pybeamtable")]
+#[derive(Clone)]
+struct PyBoolOp {
+    inner: BoolOp,
+}
+
+#[pymethods]
+impl PyBoolOp {
+    /// Whether the point x, y is within the filled space of the mask for the given table.
+    fn contains(&self, table: &PyBeamTable, x: f64, y: f64) -> PyResult<bool> {
+        self.inner.contains(&table.inner, x, y).map_err(to_py_err)
+    }
+
+    /// The mask, one list of gaps per beam.
+    #[getter]
+    fn inside(&self) -> Vec<Vec<bool>> {
+        self.inner.inside.clone()
+    }
+
+    fn __and__(&self, other: &Self) -> PyResult<Self> {
+        self.check_shape(other)?;
+        Ok(bool_op(self.inner.clone() & other.inner.clone()))
+    }
+
+    fn __or__(&self, other: &Self) -> PyResult<Self> {
+        self.check_shape(other)?;
+        Ok(bool_op(self.inner.clone() | other.inner.clone()))
+    }
+
+    fn __xor__(&self, other: &Self) -> PyResult<Self> {
+        self.check_shape(other)?;
+        Ok(bool_op(self.inner.clone() ^ other.inner.clone()))
+    }
+
+    fn __sub__(&self, other: &Self) -> PyResult<Self> {
+        self.check_shape(other)?;
+        Ok(bool_op(self.inner.clone() - other.inner.clone()))
+    }
+
+    fn __invert__(&self) -> Self {
+        bool_op(!self.inner.clone())
+    }
+
+    fn __repr__(&self) -> String {
+        format!("BoolOp({} beams)", self.inner.inside.len())
+    }
+}
+
+impl PyBoolOp {
+    /// Internal: masks combine gap by gap, so they must come from the same table.
+    fn check_shape(&self, other: &Self) -> PyResult<()> {
+        let same = self.inner.inside.len() == other.inner.inside.len()
+            && self
+                .inner
+                .inside
+                .iter()
+                .zip(&other.inner.inside)
+                .all(|(a, b)| a.len() == b.len());
+        if !same {
+            return Err(to_py_err(BeamTableError::MaskMismatch));
+        }
+        Ok(())
+    }
+}
+
+/// BeamTable over a geometry.
+#[pyclass(name = "BeamTable", module = "pybeamtable")]
+struct PyBeamTable {
+    inner: BeamTable,
+}
+
+#[pymethods]
+impl PyBeamTable {
+    #[new]
+    #[pyo3(signature = (geometry, grid=None))]
+    fn new(geometry: &PyGeomstr, grid: Option<f64>) -> Self {
+        PyBeamTable {
+            inner: BeamTable::new(geometry.inner.clone(), grid),
+        }
+    }
 
-// #[pyclass]
-// struct BeamTable {
-//     #[pyo3(get)]
-//     pub events: Vec<PyComplex>,
-//     #[pyo3(get)]
-//     pub actives: Vec<Vec<i32>>,
-// }
-//
-// #[pymethods]
-// impl BeamTable {
-//     #[new]
-//     pub fn new() -> BeamTable {
-//         BeamTable {
-//             events: Vec::new(),
-//             actives: Vec::new(),
-//         }
-//     }
-// }
-
-///
-// #[pyfunction]
-// fn build(segments: Vec<(f64, f64, f64, f64)>) -> PyResult<(Vec<(f64, f64)>, Vec<Vec<i32>>)> {
-//     let mut table = ScanBeam::from_floats(segments);
-//     let q = table.build();
-//
-//     let mut segs = Vec::new();
-//     for m in q.events.iter() {
-//         segs.push((m.x, m.y));
-//     }
-//     Ok((segs, q.actives))
-// }
+    /// Whether the table has been built.
+    #[getter]
+    fn built(&self) -> bool {
+        self.inner.is_built()
+    }
+
+    /// Build the table, without holding the GIL.
+    fn build(&mut self, py: Python<'_>) -> PyResult<()> {
+        py.allow_threads(|| self.inner.build()).map_err(to_py_err)
+    }
+
+    /// The geometry of the table, flattened and snapped once built.
+    #[getter]
+    fn geometry(&self) -> PyGeomstr {
+        PyGeomstr {
+            inner: self.inner.geometry.clone(),
+        }
+    }
+
+    /// Event points in sweep order, beam i starts at event i.
+    #[getter]
+    fn events(&self) -> Vec<(f64, f64)> {
+        self.inner.events.iter().map(|e| (e.x, e.y)).collect()
+    }
+
+    /// Active segment indexes of each beam, bottom to top.
+    #[getter]
+    fn actives(&self) -> Vec<Vec<usize>> {
+        self.inner.actives.iter().map(|a| a.to_vec()).collect()
+    }
+
+    /// Intersection points found during the build.
+    #[getter]
+    fn intersections(&self) -> Vec<(f64, f64)> {
+        self.inner
+            .intersections
+            .iter()
+            .map(|p| (p.x, p.y))
+            .collect()
+    }
+
+    /// Active segment indexes of the beam at x, y.
+    fn actives_at(&self, x: f64, y: f64) -> PyResult<Vec<usize>> {
+        self.inner
+            .actives_at(x, y)
+            .map(|a| a.to_vec())
+            .map_err(to_py_err)
+    }
+
+    /// Even/odd fill of the layer.
+    fn evenodd_fill(&self, layer: f64) -> PyBoolOp {
+        bool_op(self.inner.evenodd_fill(layer))
+    }
+
+    /// Nonzero winding fill of the layer.
+    fn nonzero_fill(&self, layer: f64) -> PyBoolOp {
+        bool_op(self.inner.nonzero_fill(layer))
+    }
+
+    /// Nonzero winding fill of all geometry.
+    fn nonzero_all(&self) -> PyBoolOp {
+        bool_op(self.inner.nonzero_all())
+    }
+
+    /// Even/odd fill of all geometry.
+    fn even_odd_ignoring_origin(&self) -> PyBoolOp {
+        bool_op(self.inner.even_odd_ignoring_origin())
+    }
+
+    /// Union of all layers.
+    fn union_all(&self) -> PyBoolOp {
+        bool_op(self.inner.union_all())
+    }
+
+    /// Space inside both layer a and layer b.
+    fn intersection(&self, a: f64, b: f64) -> PyBoolOp {
+        bool_op(self.inner.intersection(a, b))
+    }
+
+    /// Space inside layer a but not layer b.
+    fn difference(&self, a: f64, b: f64) -> PyBoolOp {
+        bool_op(self.inner.difference(a, b))
+    }
+
+    /// Space inside exactly one of layer a and layer b.
+    fn xor(&self, a: f64, b: f64) -> PyBoolOp {
+        bool_op(self.inner.xor(a, b))
+    }
+
+    /// Boundary geometry of the mask, created without holding the GIL.
+    #[pyo3(signature = (mask, greedy=false))]
+    fn create(&self, py: Python<'_>, mask: &PyBoolOp, greedy: bool) -> PyResult<PyGeomstr> {
+        let mask = mask.inner.clone();
+        let inner = py
+            .allow_threads(|| self.inner.create(mask, greedy))
+            .map_err(to_py_err)?;
+        Ok(PyGeomstr { inner })
+    }
+
+    fn __repr__(&self) -> String {
+        let state = if self.inner.is_built() {
+            "built"
+        } else {
+            "unbuilt"
+        };
+        format!(
+            "BeamTable({} segments, {} events, {})",
+            self.inner.geometry.segments.len(),
+            self.inner.events.len(),
+            state
+        )
+    }
+
+    /// Pickled as the table serialized to MessagePack, a built table loads without a rebuild.
+    /// The constructor arguments are placeholders, the state replaces the whole table.
+    fn __getnewargs__(&self) -> (PyGeomstr, Option<f64>) {
+        let geometry = PyGeomstr {
+            inner: Geomstr::new(),
+        };
+        (geometry, None)
+    }
+
+    fn __getstate__<'py>(&self, py: Python<'py>) -> PyResult<&'py PyBytes> {
+        let bytes = self
+            .inner
+            .to_bytes()
+            .map_err(|err| PyValueError::new_err(err.to_string()))?;
+        Ok(PyBytes::new(py, &bytes))
+    }
+
+    fn __setstate__(&mut self, state: &[u8]) -> PyResult<()> {
+        self.inner =
+            BeamTable::from_bytes(state).map_err(|err| PyValueError::new_err(err.to_string()))?;
+        Ok(())
+    }
+}
+
+/// Union of all layers. Takes a list of segment tuples or an N x 5 complex128 array and returns
+/// the same kind.
 #[pyfunction]
-fn union(
-    segments: Vec<((f64, f64), (f64, f64), (f64, f64), (f64, f64), (f64, f64))>,
-) -> Vec<((f64, f64), (f64, f64), (f64, f64), (f64, f64), (f64, f64))> {
-    let mut table = BeamTable::new(Geomstr::from_segments(segments));
-    table.build();
-    let bo = table.union_all();
-    table.create(bo, true).segments
+fn union(py: Python<'_>, segments: &PyAny) -> PyResult<PyObject> {
+    let array = is_buffer(segments);
+    let geometry = if array {
+        geomstr_from_buffer(segments)?
+    } else {
+        Geomstr::from_tuples(segments.extract()?)
+    };
+    let geometry = py
+        .allow_threads(|| {
+            let mut table = BeamTable::new(geometry, None);
+            table.build()?;
+            let bo = table.union_all();
+            table.create(bo, true)
+        })
+        .map_err(to_py_err)?;
+    if array {
+        geomstr_to_numpy(py, &geometry)
+    } else {
+        Ok(geometry.to_tuples().into_py(py))
+    }
+}
+
+/// Build a table for each geometry in parallel on native threads, without holding the GIL.
+/// Threads defaults to the available parallelism.
+#[pyfunction]
+#[pyo3(signature = (geometries, grid=None, threads=None))]
+fn build_tables(
+    py: Python<'_>,
+    geometries: Vec<PyRef<PyGeomstr>>,
+    grid: Option<f64>,
+    threads: Option<usize>,
+) -> PyResult<Vec<PyBeamTable>> {
+    let mut tables: Vec<BeamTable> = geometries
+        .iter()
+        .map(|geometry| BeamTable::new(geometry.inner.clone(), grid))
+        .collect();
+    let threads = threads
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()))
+        .clamp(1, tables.len().max(1));
+    py.allow_threads(|| build_parallel(&mut tables, threads))
+        .map_err(|(index, err)| PyValueError::new_err(format!("geometry {}: {}", index, err)))?;
+    Ok(tables
+        .into_iter()
+        .map(|inner| PyBeamTable { inner })
+        .collect())
+}
+
+/// Internal: build the tables on the given number of threads, each taking the next unbuilt
+/// table until none are left. A failed worker stops, the lowest failing index is returned.
+fn build_parallel(tables: &mut [BeamTable], threads: usize) -> Result<(), (usize, BeamTableError)> {
+    let queue = Mutex::new(tables.iter_mut().enumerate());
+    let mut result = Ok(());
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| loop {
+                    let next = queue.lock().unwrap().next();
+                    let Some((index, table)) = next else {
+                        return Ok(());
+                    };
+                    table.build().map_err(|err| (index, err))?;
+                })
+            })
+            .collect();
+        for worker in workers {
+            let worker_result = worker
+                .join()
+                .unwrap_or_else(|e| std::panic::resume_unwind(e));
+            if let Err((index, err)) = worker_result {
+                if !matches!(result, Err((first, _)) if first < index) {
+                    result = Err((index, err));
+                }
+            }
+        }
+    });
+    result
+}
+
+/// Internal: wrap a mask for Python.
+fn bool_op(inner: BoolOp) -> PyBoolOp {
+    PyBoolOp { inner }
+}
+
+/// Table errors are raised as ValueError.
+fn to_py_err(err: BeamTableError) -> PyErr {
+    PyValueError::new_err(err.to_string())
 }
 
 /// A Python module implemented in Rust.
 #[pymodule]
 fn pybeamtable(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(union, m)?)?;
-    // m.add_function(wrap_pyfunction!(complex_test, m)?)?;
-    // m.add_class::<BeamTable>()?;
+    m.add_function(wrap_pyfunction!(build_tables, m)?)?;
+    m.add_class::<PyGeomstr>()?;
+    m.add_class::<PyBeamTable>()?;
+    m.add_class::<PyBoolOp>()?;
     Ok(())
 }
@@ -0,0 +1,102 @@
+"""Type stubs for the pybeamtable extension module."""
+
+from typing import Any, List, Optional, Sequence, Tuple, overload
+
+Point = Tuple[float, float]
+SegmentTuple = Tuple[Point, Point, Tuple[float, float], Point, Point]
+"""start, c0, (type, settings), c1, end"""
+
+class Geomstr:
+    """Geometry as a list of geomstr segments."""
+
+    def __init__(self, segments: Optional[Sequence[SegmentTuple]] = None) -> None: ...
+    @staticmethod
+    def from_array(array: Any) -> Geomstr:
+        """Geometry from an N x 5 complex128 array in meerk40t's geomstr layout."""
+    def as_array(self) -> Any:
+        """The segments as an N x 5 complex128 numpy array."""
+    def line(self, p0: Point, p1: Point, settings: float = 0.0) -> None:
+        """Add a line from p0 to p1."""
+    def rect(
+        self, x: float, y: float, width: float, height: float, settings: float = 0.0
+    ) -> None:
+        """Add a rectangle."""
+    @property
+    def segments(self) -> List[SegmentTuple]:
+        """The segments as geomstr tuples."""
+    def __len__(self) -> int: ...
+    def __getnewargs__(self) -> Tuple[List[SegmentTuple]]: ...
+
+class BoolOp:
+    """Fill mask over the beams of a table."""
+
+    def contains(self, table: BeamTable, x: float, y: float) -> bool:
+        """Whether the point x, y is within the filled space of the mask for the given table."""
+    @property
+    def inside(self) -> List[List[bool]]:
+        """The mask, one list of gaps per beam."""
+    def __and__(self, other: BoolOp) -> BoolOp: ...
+    def __or__(self, other: BoolOp) -> BoolOp: ...
+    def __xor__(self, other: BoolOp) -> BoolOp: ...
+    def __sub__(self, other: BoolOp) -> BoolOp: ...
+    def __invert__(self) -> BoolOp: ...
+
+class BeamTable:
+    """BeamTable over a geometry."""
+
+    def __init__(self, geometry: Geomstr, grid: Optional[float] = None) -> None: ...
+    @property
+    def built(self) -> bool:
+        """Whether the table has been built."""
+    def build(self) -> None:
+        """Build the table, without holding the GIL."""
+    @property
+    def geometry(self) -> Geomstr:
+        """The geometry of the table, flattened and snapped once built."""
+    @property
+    def events(self) -> List[Point]:
+        """Event points in sweep order, beam i starts at event i."""
+    @property
+    def actives(self) -> List[List[int]]:
+        """Active segment indexes of each beam, bottom to top."""
+    @property
+    def intersections(self) -> List[Point]:
+        """Intersection points found during the build."""
+    def actives_at(self, x: float, y: float) -> List[int]:
+        """Active segment indexes of the beam at x, y."""
+    def evenodd_fill(self, layer: float) -> BoolOp:
+        """Even/odd fill of the layer."""
+    def nonzero_fill(self, layer: float) -> BoolOp:
+        """Nonzero winding fill of the layer."""
+    def nonzero_all(self) -> BoolOp:
+        """Nonzero winding fill of all geometry."""
+    def even_odd_ignoring_origin(self) -> BoolOp:
+        """Even/odd fill of all geometry."""
+    def union_all(self) -> BoolOp:
+        """Union of all layers."""
+    def intersection(self, a: float, b: float) -> BoolOp:
+        """Space inside both layer a and layer b."""
+    def difference(self, a: float, b: float) -> BoolOp:
+        """Space inside layer a but not layer b."""
+    def xor(self, a: float, b: float) -> BoolOp:
+        """Space inside exactly one of layer a and layer b."""
+    def create(self, mask: BoolOp, greedy: bool = False) -> Geomstr:
+        """Boundary geometry of the mask, created without holding the GIL."""
+    def __getnewargs__(self) -> Tuple[Geomstr, Optional[float]]: ...
+    def __getstate__(self) -> bytes: ...
+    def __setstate__(self, state: bytes) -> None: ...
+
+@overload
+def union(segments: Sequence[SegmentTuple]) -> List[SegmentTuple]: ...
+@overload
+def union(segments: Any) -> Any:
+    """Union of all layers. Takes a list of segment tuples or an N x 5 complex128 array and
+    returns the same kind."""
+
+def build_tables(
+    geometries: Sequence[Geomstr],
+    grid: Optional[float] = None,
+    threads: Optional[int] = None,
+) -> List[BeamTable]:
+    """Build a table for each geometry in parallel on native threads, without holding the GIL.
+    Threads defaults to the available parallelism."""
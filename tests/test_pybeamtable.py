@@ -0,0 +1,206 @@
+import ast
+import pathlib
+import pickle
+import threading
+
+import pytest
+
+import pybeamtable
+from pybeamtable import BeamTable, BoolOp, Geomstr
+
+TYPE_LINE = 41.0
+
+
+def overlapping_squares():
+    g = Geomstr()
+    g.rect(0, 0, 10, 10, 1.0)
+    g.rect(5, 5, 10, 10, 2.0)
+    return g
+
+
+def length(geometry):
+    return sum(
+        ((end[0] - start[0]) ** 2 + (end[1] - start[1]) ** 2) ** 0.5
+        for start, _, _, _, end in geometry.segments
+    )
+
+
+def built_table(geometry=None):
+    table = BeamTable(geometry or overlapping_squares())
+    table.build()
+    return table
+
+
+def test_geomstr():
+    g = Geomstr()
+    assert len(g) == 0
+    g.line((0, 0), (1, 2), 3.0)
+    g.rect(0, 0, 1, 1)
+    assert len(g) == 5
+    assert g.segments[0] == ((0, 0), (0, 0), (TYPE_LINE, 3.0), (0, 0), (1, 2))
+    assert Geomstr(g.segments).segments == g.segments
+    assert repr(g) == "Geomstr(5 segments)"
+
+
+def test_beamtable_build():
+    table = BeamTable(overlapping_squares())
+    assert not table.built
+    assert repr(table) == "BeamTable(8 segments, 0 events, unbuilt)"
+    table.build()
+    assert table.built
+    assert len(table.events) == len(table.actives) == 10
+    assert (5.0, 10.0) in table.intersections
+    assert (10.0, 5.0) in table.intersections
+    assert table.actives_at(6, 6) == table.actives[4]
+    assert len(table.geometry) == 8
+    assert repr(table) == "BeamTable(8 segments, 10 events, built)"
+
+
+def test_fills():
+    table = built_table()
+    inside = {
+        "evenodd_fill": table.evenodd_fill(1.0),
+        "nonzero_fill": table.nonzero_fill(1.0),
+        "nonzero_all": table.nonzero_all(),
+        "even_odd_ignoring_origin": table.even_odd_ignoring_origin(),
+        "union_all": table.union_all(),
+        "intersection": table.intersection(1.0, 2.0),
+        "difference": table.difference(1.0, 2.0),
+        "xor": table.xor(1.0, 2.0),
+    }
+    expected = {
+        "evenodd_fill": (True, True, False),
+        "nonzero_fill": (True, True, False),
+        "nonzero_all": (True, True, True),
+        "even_odd_ignoring_origin": (True, False, True),
+        "union_all": (True, True, True),
+        "intersection": (False, True, False),
+        "difference": (True, False, False),
+        "xor": (True, False, True),
+    }
+    for name, mask in inside.items():
+        assert isinstance(mask, BoolOp)
+        points = tuple(mask.contains(table, p, p) for p in (2, 7, 12))
+        assert points == expected[name], name
+        assert not mask.contains(table, 20, 20)
+
+
+def test_boolop_operators():
+    table = built_table()
+    a = table.evenodd_fill(1.0)
+    b = table.evenodd_fill(2.0)
+    assert (a & b).inside == table.intersection(1.0, 2.0).inside
+    assert (a | b).inside == table.union_all().inside
+    assert (a ^ b).inside == table.xor(1.0, 2.0).inside
+    assert (a - b).inside == table.difference(1.0, 2.0).inside
+    assert (~a).contains(table, 12, 12)
+    assert not (~a).contains(table, 2, 2)
+    assert repr(a) == "BoolOp(10 beams)"
+
+    other = built_table(Geomstr([((0, 0), (0, 0), (TYPE_LINE, 1.0), (0, 0), (1, 1))]))
+    with pytest.raises(ValueError):
+        a & other.union_all()
+    with pytest.raises(ValueError):
+        table.create(other.union_all())
+
+
+def test_create():
+    table = built_table()
+    union = table.create(table.union_all())
+    assert length(union) == pytest.approx(60.0)
+    assert sorted(union.segments) == sorted(pybeamtable.union(table.geometry.segments))
+    greedy = table.create(table.intersection(1.0, 2.0), greedy=True)
+    assert length(greedy) == pytest.approx(20.0)
+
+
+def test_errors():
+    with pytest.raises(ValueError):
+        BeamTable(Geomstr()).create(built_table().union_all())
+    bad = Geomstr()
+    bad.line((0, 0), (float("nan"), 1))
+    with pytest.raises(ValueError):
+        BeamTable(bad).build()
+    with pytest.raises(ValueError):
+        built_table().actives_at(float("inf"), 0)
+
+
+def test_build_tables():
+    geometries = [overlapping_squares() for _ in range(5)]
+    tables = pybeamtable.build_tables(geometries, threads=3)
+    assert all(table.built for table in tables)
+    assert [t.events for t in tables] == [built_table().events] * 5
+    bad = Geomstr()
+    bad.line((0, 0), (float("nan"), 1))
+    with pytest.raises(ValueError, match="geometry 1"):
+        pybeamtable.build_tables([overlapping_squares(), bad])
+
+
+def test_threads():
+    tables = [BeamTable(overlapping_squares()) for _ in range(4)]
+    threads = [threading.Thread(target=table.build) for table in tables]
+    for thread in threads:
+        thread.start()
+    for thread in threads:
+        thread.join()
+    assert all(table.built for table in tables)
+
+
+def test_pickle():
+    g = overlapping_squares()
+    assert pickle.loads(pickle.dumps(g)).segments == g.segments
+
+    unbuilt = pickle.loads(pickle.dumps(BeamTable(g, 0.5)))
+    assert not unbuilt.built
+    assert len(unbuilt.geometry) == 8
+
+    unbuilt.build()
+    assert unbuilt.built
+
+    table = built_table()
+    assert isinstance(table.__getstate__(), bytes)
+    loaded = pickle.loads(pickle.dumps(table))
+    assert loaded.built
+    assert loaded.geometry.segments == table.geometry.segments
+    assert loaded.events == table.events
+    assert loaded.actives == table.actives
+    mask = loaded.union_all()
+    assert mask.contains(loaded, 12, 12)
+    with pytest.raises(ValueError):
+        BeamTable(g).__setstate__(b"not a table")
+
+
+def test_arrays():
+    np = pytest.importorskip("numpy")
+    g = overlapping_squares()
+    array = g.as_array()
+    assert array.dtype == np.complex128
+    assert array.shape == (8, 5)
+    assert array[0, 2] == complex(TYPE_LINE, 1.0)
+    assert array[0, 4] == complex(10, 0)
+    assert Geomstr.from_array(array).segments == g.segments
+
+    result = pybeamtable.union(array)
+    assert isinstance(result, np.ndarray)
+    assert result.shape == (len(pybeamtable.union(g.segments)), 5)
+    with pytest.raises(ValueError):
+        Geomstr.from_array(np.zeros((3, 4), dtype=np.complex128))
+
+
+def test_stubs_cover_bindings():
+    stub = pathlib.Path(__file__).parent.parent / "pybeamtable.pyi"
+    tree = ast.parse(stub.read_text())
+    stubbed = {}
+    for node in tree.body:
+        if isinstance(node, ast.ClassDef):
+            stubbed[node.name] = {n.name for n in node.body if isinstance(n, ast.FunctionDef)}
+        elif isinstance(node, ast.FunctionDef):
+            stubbed[node.name] = set()
+    for name in dir(pybeamtable):
+        if name.startswith("_"):
+            continue
+        assert name in stubbed, name
+        value = getattr(pybeamtable, name)
+        if isinstance(value, type):
+            for attr in vars(value):
+                if not attr.startswith("_") or attr in ("__getnewargs__", "__getstate__"):
+                    assert attr in stubbed[name], f"{name}.{attr}"